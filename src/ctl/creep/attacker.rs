@@ -0,0 +1,97 @@
+//!
+//! Controls attacker creeps sent after structures in other rooms
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Creep, ReturnCode, RoomName, StructureSpawn};
+use screeps::constants::*;
+use screeps::memory;
+
+use crate::metrics;
+use crate::util;
+use crate::invader::CoreSighting;
+
+use super::body;
+use super::boosting;
+use super::movement;
+use super::spawning;
+use super::types::{CoreAttacker, CreepInfo};
+
+
+/// tries to spawn an attacker sized to take down an invader core
+pub fn spawn_core_attacker(spawn: &StructureSpawn, core: &CoreSighting) -> Result<(), String> {
+    let room = spawn.room();
+    let parts = body::scale(CoreAttacker::parts(), room.energy_available(), core.attack_units());
+    if parts.is_empty() {
+        return Err("not enough energy for core attacker".to_string());
+    }
+
+    let mem = memory::MemoryReference::new();
+    mem.set("role", CoreAttacker::role());
+    mem.set("home", room.name().to_string());
+    boosting::request(&mem, CoreAttacker::boosts(), CoreAttacker::boost_policy());
+    mem.set("target", core.room.to_string());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        info!("spawning core attacker with {} parts for room {}", parts.len(), core.room);
+        metrics::inc_attackers(1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn core attacker: {:?}", res))
+    }
+}
+
+/// Counts the core attackers alive (or spawning) that target the room
+pub fn core_attackers_for(room_name: RoomName) -> u32 {
+    util::count_creeps(CoreAttacker::role(), "target", &room_name.to_string())
+}
+
+
+/// runs a core attacker
+pub fn run_core_attacker(creep: Creep) {
+    trace!("running core attacker {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let target_room = match creep.memory().string("target").ok().flatten()
+        .and_then(|t| RoomName::from_str(&t).ok()) {
+        Some(t) => t,
+        None => {
+            warn!("core attacker {} has no target room", creep.name());
+            spawning::recycle(&creep);
+            return;
+        }
+    };
+
+    if creep.room().name() != target_room {
+        movement::travel_to_room(&creep, target_room);
+        return;
+    }
+
+    // break any rampart over the core first
+    let structures = creep.room().find(find::HOSTILE_STRUCTURES);
+    let core = structures.iter().find(|s| s.structure_type() == StructureType::InvaderCore);
+    let target = match core {
+        Some(core) => structures.iter()
+            .find(|s| s.structure_type() == StructureType::Rampart && s.pos().is_equal_to(core))
+            .unwrap_or(core),
+        None => {
+            // job's done
+            creep.say("♻️ Recycle", false);
+            spawning::recycle(&creep);
+            return;
+        }
+    };
+
+    if creep.attack(target) == ReturnCode::NotInRange {
+        creep.move_to(target);
+    }
+}
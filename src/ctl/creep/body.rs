@@ -0,0 +1,68 @@
+//!
+//! Creep body composition & sizing
+//!
+
+use screeps::constants::MAX_CREEP_SIZE;
+use screeps::creep::Part;
+use screeps::ResourceType;
+
+
+/// get the energy cost of a set of body parts
+pub fn cost(parts: &[Part]) -> u32 {
+    parts.iter().fold(0, |cost, part| cost + part.cost())
+}
+
+/// Repeats a unit of parts as many times as the energy allows, up to `max_units` and
+/// the creep size limit. Returns an empty body if not even one unit is affordable.
+pub fn scale(unit: &[Part], energy: u32, max_units: u32) -> Vec<Part> {
+    let unit_cost = cost(unit);
+    if unit.is_empty() || unit_cost == 0 {
+        return Vec::new();
+    }
+
+    let units = (energy / unit_cost)
+        .min(max_units)
+        .min(MAX_CREEP_SIZE / unit.len() as u32);
+
+    let mut body: Vec<Part> = unit.iter()
+        .cycle()
+        .take(unit.len() * units as usize)
+        .cloned()
+        .collect();
+    body.sort_by_key(|p| part_order(*p));
+    body
+}
+
+/// Ordering used when laying out a body, so that parts which should absorb damage
+/// come first and the parts keeping the creep useful are lost last
+fn part_order(part: Part) -> u8 {
+    match part {
+        Part::Tough => 0,
+        Part::Work => 1,
+        Part::Carry => 2,
+        Part::Attack => 3,
+        Part::RangedAttack => 4,
+        Part::Claim => 5,
+        Part::Move => 6,
+        Part::Heal => 7,
+    }
+}
+
+/// Effect multiplier of a boosted part on its main action
+/// (attack, ranged attack, heal), or 1 if the boost doesn't apply to the part
+pub fn boost_multiplier(part: Part, boost: Option<ResourceType>) -> u32 {
+    use ResourceType::*;
+
+    match (part, boost) {
+        (Part::Attack, Some(UtriumHydride)) => 2,
+        (Part::Attack, Some(UtriumAcid)) => 3,
+        (Part::Attack, Some(CatalyzedUtriumAcid)) => 4,
+        (Part::RangedAttack, Some(KeaniumOxide)) => 2,
+        (Part::RangedAttack, Some(KeaniumAlkalide)) => 3,
+        (Part::RangedAttack, Some(CatalyzedKeaniumAlkalide)) => 4,
+        (Part::Heal, Some(LemergiumOxide)) => 2,
+        (Part::Heal, Some(LemergiumAlkalide)) => 3,
+        (Part::Heal, Some(CatalyzedLemergiumAlkalide)) => 4,
+        _ => 1,
+    }
+}
@@ -0,0 +1,143 @@
+//!
+//! Boosting new creeps at the labs before they start their job
+//!
+//! A role's boosts go into the creep's memory when it's spawned. While it spawns it holds a
+//! lab for each of them so carriers can fill the labs in time, then it visits them in turn.
+//! A boost the labs can't give is dropped, or waited for up to `boost.max_wait` ticks,
+//! depending on the role's policy.
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{Creep, ResourceType, ReturnCode};
+use screeps::constants::*;
+use screeps::memory::MemoryReference;
+
+use crate::labs;
+use crate::util::{self, config};
+
+use super::types::BoostPolicy;
+
+
+/// Ticks a lab stays reserved past the last tick its creep asked for it
+const HOLD_TICKS: u32 = 10;
+
+
+/// Puts a role's boosts into a new creep's memory, for it to pick up once it's spawned
+pub fn request(mem: &MemoryReference, boosts: &[ResourceType], policy: BoostPolicy) {
+    if boosts.is_empty() {
+        return;
+    }
+    let names: Vec<String> = boosts.iter().map(|b| util::resource_name(*b)).collect();
+    mem.set("boosts", names);
+    mem.set("boost_wait", policy == BoostPolicy::Wait);
+}
+
+/// Gets the boosts a creep still has to pick up
+fn pending(mem: &MemoryReference) -> Vec<ResourceType> {
+    mem.arr::<String>("boosts").ok().flatten().unwrap_or_default().iter()
+        .filter_map(|b| ResourceType::from_str(b).ok())
+        .collect()
+}
+
+/// Marks a boost as done with, whether the creep got it or not
+fn drop_boost(mem: &MemoryReference, boost: ResourceType) {
+    let names: Vec<String> = pending(mem).into_iter()
+        .filter(|b| *b != boost)
+        .map(util::resource_name)
+        .collect();
+    if names.is_empty() {
+        for key in ["boosts", "boost_wait", "boost_since"].iter() {
+            mem.del(key);
+        }
+    } else {
+        mem.set("boosts", names);
+    }
+}
+
+/// Boosts the creep if it still has boosts to pick up.
+/// Returns whether the creep is busy with that, in which case its role shouldn't run.
+pub fn run(creep: &Creep) -> bool {
+    let mem = creep.memory();
+    let boosts = pending(&mem);
+    let boost = match boosts.first() {
+        Some(b) => *b,
+        None => return false,
+    };
+
+    let room = creep.room();
+    let time = screeps::game::time();
+    if labs::setup(&room).is_none() {
+        // nowhere to get boosted, don't bother waiting
+        for boost in boosts {
+            drop_boost(&mem, boost);
+        }
+        return false;
+    }
+
+    if creep.spawning() {
+        for boost in boosts {
+            labs::reserve(&room, boost, time + HOLD_TICKS);
+        }
+        return true;
+    }
+
+    let parts = labs::part_for(boost)
+        .map(|part| creep.body().iter().filter(|p| p.part == part && p.boost.is_none()).count() as u32)
+        .unwrap_or(0);
+    if parts == 0 {
+        drop_boost(&mem, boost);
+        return true;
+    }
+
+    let since = match mem.i32("boost_since").ok().flatten() {
+        Some(t) => t as u32,
+        None => {
+            mem.set("boost_since", time);
+            time
+        }
+    };
+
+    let wait = mem.bool("boost_wait") && time.saturating_sub(since) < config::u32_or("boost.max_wait", 200);
+    let lab = match labs::reserve(&room, boost, time + HOLD_TICKS) {
+        Some(l) => l,
+        // roles that wait try again next tick, in case a lab frees up
+        None if wait => return true,
+        None => {
+            debug!("no lab free to boost {} with {}", creep.name(), util::resource_name(boost));
+            drop_boost(&mem, boost);
+            return true;
+        }
+    };
+
+    // parts the lab can boost right now; short labs boost what they can unless the role waits
+    let boostable = (lab.store_of(boost) / LAB_BOOST_MINERAL)
+        .min(lab.store_of(ResourceType::Energy) / LAB_BOOST_ENERGY)
+        .min(parts);
+    if boostable < parts && wait {
+        if !creep.pos().is_near_to(&lab) {
+            creep.move_to(&lab);
+        }
+        return true;
+    }
+    if boostable == 0 {
+        debug!("{} going without {}", creep.name(), util::resource_name(boost));
+        drop_boost(&mem, boost);
+        return true;
+    }
+
+    match lab.boost_creep(creep, Some(boostable)) {
+        ReturnCode::NotInRange => {
+            creep.move_to(&lab);
+        },
+        ReturnCode::Ok => drop_boost(&mem, boost),
+        r => {
+            warn!("couldn't boost {} with {}: {:?}", creep.name(), util::resource_name(boost), r);
+            drop_boost(&mem, boost);
+        }
+    }
+    true
+}
@@ -8,48 +8,38 @@ use log::*;
 
 use screeps::prelude::*;
 use screeps::{find};
-use screeps::{ConstructionSite, Creep, HasStore, ResourceType, ReturnCode, SpawnOptions, StructureSpawn};
+use screeps::{ConstructionSite, Creep, HasStore, ResourceType, ReturnCode, StructureSpawn};
 use screeps::memory;
 
+use crate::downgrade;
 use crate::util;
 use crate::metrics;
+use crate::nuke;
 
+use super::body;
+use super::spawning;
 use super::types::{BasicBuilder, CreepInfo};
 
 
 
-/// tries to spawn a basic builder
+/// tries to spawn a basic builder, as big as the room's energy allows
 pub fn spawn_basic_builder(spawn: &StructureSpawn) -> Result<(), String> {
-    if spawn.energy() >= BasicBuilder::cost() {
-        // create a unique name, spawn.
-        let name_base = screeps::game::time();
-        let mut additional = 0;
-
-        // set the role of the creep on spawn
-        let mem = memory::MemoryReference::new();
-        mem.set("role", BasicBuilder::role());
-        let opts = SpawnOptions::new().memory(mem);
-
-        // loop until we get a valid name
-        let res = loop {
-            let name = format!("{}-{}", name_base, additional);
-            let res = spawn.spawn_creep_with_options(&BasicBuilder::parts(), &name, &opts);
-
-            if res == ReturnCode::NameExists {
-                additional += 1;
-            } else {
-                metrics::inc_builders(1);
-                break res;
-            }
-        };
+    let room = spawn.room();
+    let parts = body::scale(BasicBuilder::parts(), room.energy_available(), BasicBuilder::max_units());
+    if parts.is_empty() {
+        return Err("not enough energy for basic builder".to_string());
+    }
 
-        if res != ReturnCode::Ok {
-            warn!("couldn't spawn: {:?}", res);
-        }
+    let mem = memory::MemoryReference::new();
+    mem.set("role", BasicBuilder::role());
+    mem.set("home", room.name().to_string());
 
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        metrics::inc_builders(1);
         Ok(())
     } else {
-        Err("Failed to ".to_string())
+        Err(format!("couldn't spawn basic builder: {:?}", res))
     }
 }
 
@@ -64,6 +54,38 @@ pub fn run_basic_builder(creep: Creep) {
     }
 
     if creep.memory().bool("building") {
+        // keeping the controller from downgrading comes before any building or repairs
+        if downgrade::at_risk(&creep.room()) {
+            if let Some(controller) = creep.room().controller() {
+                match creep.upgrade_controller(&controller) {
+                    ReturnCode::NotInRange => {
+                        creep.move_to(&controller);
+                    },
+                    ReturnCode::NotEnough => {
+                        creep.say("📦 Collect", false);
+                        creep.memory().set("building", false);
+                    },
+                    _ => ()
+                }
+                return;
+            }
+        }
+
+        // shoring up ramparts ahead of a nuke comes before any construction
+        if let Some(rampart) = nuke::reinforce_target(&creep.room()) {
+            match creep.repair(&rampart) {
+                ReturnCode::NotInRange => {
+                    creep.move_to(&rampart);
+                },
+                ReturnCode::NotEnough => {
+                    creep.say("📦 Collect", false);
+                    creep.memory().set("building", false);
+                },
+                _ => ()
+            }
+            return;
+        }
+
         let target_opt = util::obj_from_mem_id::<ConstructionSite>(creep.memory(), "destId");
 
         // if the creep has no target, give it one
@@ -0,0 +1,163 @@
+//!
+//! Controls carrier creeps, moving resources between structures in their room
+//!
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{Creep, ResourceType, ReturnCode, StructureSpawn};
+use screeps::memory;
+
+use crate::logistics::{self, HaulTask};
+use crate::metrics;
+use crate::util;
+
+use super::body;
+use super::spawning;
+use super::types::{Carrier, CreepInfo};
+
+
+/// tries to spawn a carrier
+pub fn spawn_carrier(spawn: &StructureSpawn) -> Result<(), String> {
+    let room = spawn.room();
+    let parts = body::scale(Carrier::parts(), room.energy_available(), Carrier::max_units());
+    if parts.is_empty() {
+        return Err("not enough energy for carrier".to_string());
+    }
+
+    let mem = memory::MemoryReference::new();
+    mem.set("role", Carrier::role());
+    mem.set("home", room.name().to_string());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        metrics::inc_carriers(1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn carrier: {:?}", res))
+    }
+}
+
+/// runs a carrier, working through its room's haul tasks one load at a time
+pub fn run_carrier(creep: Creep) {
+    trace!("running carrier {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let mem = creep.memory();
+    let task = match HaulTask::load(&mem) {
+        Some(t) => t,
+        None => {
+            if creep.store_used_capacity(None) > 0 {
+                // leftovers from an unfinished task
+                unload(&creep);
+                return;
+            }
+            match logistics::next_task(&creep.room()) {
+                Some(t) => {
+                    t.save(&mem);
+                    t
+                },
+                None => return,
+            }
+        }
+    };
+
+    if creep.store_of(task.resource) == 0 {
+        pick_up(&creep, &task);
+    } else {
+        drop_off(&creep, &task);
+    }
+}
+
+fn pick_up(creep: &Creep, task: &HaulTask) {
+    if creep.store_used_capacity(None) > 0 {
+        // carrying something else, get rid of it first
+        unload(creep);
+        return;
+    }
+
+    let from = match util::structure_from_id(&task.from) {
+        Some(s) => s,
+        None => {
+            HaulTask::clear(&creep.memory());
+            return;
+        }
+    };
+    let available = from.as_has_store().map(|s| s.store_of(task.resource)).unwrap_or(0);
+    let amount = task.amount.min(creep.store_free_capacity(Some(task.resource))).min(available);
+    if amount == 0 {
+        HaulTask::clear(&creep.memory());
+        return;
+    }
+
+    let r = match from.as_withdrawable() {
+        Some(w) => creep.withdraw_amount(w, task.resource, amount),
+        None => ReturnCode::InvalidTarget,
+    };
+    match r {
+        ReturnCode::Ok => (),
+        ReturnCode::NotInRange => {
+            creep.move_to(&from);
+        },
+        r => {
+            warn!("carrier {} couldn't withdraw from {}: {:?}", creep.name(), task.from, r);
+            HaulTask::clear(&creep.memory());
+        }
+    }
+}
+
+fn drop_off(creep: &Creep, task: &HaulTask) {
+    let to = match util::structure_from_id(&task.to) {
+        Some(s) => s,
+        None => {
+            HaulTask::clear(&creep.memory());
+            return;
+        }
+    };
+
+    let r = match to.as_transferable() {
+        Some(t) => creep.transfer_all(t, task.resource),
+        None => ReturnCode::InvalidTarget,
+    };
+    match r {
+        ReturnCode::NotInRange => {
+            creep.move_to(&to);
+        },
+        ReturnCode::Ok => HaulTask::clear(&creep.memory()),
+        r => {
+            warn!("carrier {} couldn't deliver to {}: {:?}", creep.name(), task.to, r);
+            HaulTask::clear(&creep.memory());
+        }
+    }
+}
+
+/// Puts whatever the carrier holds into storage, or the terminal if there's no storage
+fn unload(creep: &Creep) {
+    let room = creep.room();
+    let resource = creep.store_types().into_iter().next().unwrap_or(ResourceType::Energy);
+    let r = match (room.storage(), room.terminal()) {
+        (Some(storage), _) => {
+            let r = creep.transfer_all(&storage, resource);
+            if r == ReturnCode::NotInRange {
+                creep.move_to(&storage);
+            }
+            r
+        },
+        (None, Some(terminal)) => {
+            let r = creep.transfer_all(&terminal, resource);
+            if r == ReturnCode::NotInRange {
+                creep.move_to(&terminal);
+            }
+            r
+        },
+        (None, None) => {
+            creep.drop(resource, None)
+        },
+    };
+    if r != ReturnCode::Ok && r != ReturnCode::NotInRange {
+        warn!("carrier {} couldn't unload: {:?}", creep.name(), r);
+    }
+}
@@ -0,0 +1,92 @@
+//!
+//! Controls claimer creeps, taking the controller of a room we're expanding into
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{Creep, ReturnCode, RoomName, StructureSpawn};
+use screeps::memory;
+
+use crate::expansion;
+use crate::metrics;
+
+use super::movement;
+use super::spawning;
+use super::types::{Claimer, CreepInfo};
+
+
+/// tries to spawn a claimer for the room being expanded into
+pub fn spawn_claimer(spawn: &StructureSpawn, target: RoomName) -> Result<(), String> {
+    let room = spawn.room();
+    if room.energy_available() < Claimer::cost() {
+        return Err("not enough energy for claimer".to_string());
+    }
+
+    let mem = memory::MemoryReference::new();
+    mem.set("role", Claimer::role());
+    mem.set("home", room.name().to_string());
+    mem.set("target", target.to_string());
+
+    let res = spawning::spawn_with_memory(spawn, Claimer::parts(), mem);
+    if res == ReturnCode::Ok {
+        info!("spawning claimer for room {}", target);
+        metrics::inc_expansion_creeps(1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn claimer: {:?}", res))
+    }
+}
+
+/// runs a claimer
+pub fn run_claimer(creep: Creep) {
+    trace!("running claimer {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let target = match creep.memory().string("target").ok().flatten()
+        .and_then(|t| RoomName::from_str(&t).ok()) {
+        Some(t) => t,
+        None => {
+            warn!("claimer {} has no target room", creep.name());
+            spawning::recycle(&creep);
+            return;
+        }
+    };
+
+    if creep.room().name() != target {
+        movement::travel_to_room(&creep, target);
+        return;
+    }
+
+    let controller = match creep.room().controller() {
+        Some(c) => c,
+        None => {
+            expansion::abandon("room has no controller");
+            creep.suicide();
+            return;
+        }
+    };
+
+    if controller.my() {
+        // claimed, nothing left for a claimer to do here
+        creep.suicide();
+        return;
+    }
+
+    match creep.claim_controller(&controller) {
+        ReturnCode::Ok => info!("claimed controller in room {}", target),
+        ReturnCode::NotInRange => {
+            creep.move_to(&controller);
+        },
+        r @ ReturnCode::GclNotEnough | r @ ReturnCode::InvalidTarget => {
+            expansion::abandon(&format!("couldn't claim controller: {:?}", r));
+            creep.suicide();
+        },
+        r => warn!("claimer {} couldn't claim {}: {:?}", creep.name(), target, r),
+    }
+}
@@ -0,0 +1,187 @@
+//!
+//! Controls defender creeps
+//!
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find, look};
+use screeps::{Attackable, Creep, ReturnCode, StructureSpawn};
+use screeps::constants::*;
+use screeps::memory;
+
+use crate::metrics;
+use crate::defense::ThreatAssessment;
+
+use super::body;
+use super::boosting;
+use super::spawning;
+use super::types::{CreepInfo, HealerDefender, MeleeDefender, RangedDefender};
+
+
+/// Hit points of a single body part
+const PART_HITS: u32 = 100;
+
+/// Ticks a defender is expected to hold out under fire before the hostiles go down
+const FIGHT_TICKS: u32 = 20;
+
+
+/// Sizing of a defender body against an assessed threat
+pub trait DefenderSizing: CreepInfo {
+    /// number of body units needed to beat the threat
+    fn units_needed(threat: &ThreatAssessment) -> u32;
+}
+
+/// Units a body needs for its hit points to take what the hostiles deal over the fight
+fn units_to_survive<T: CreepInfo>(threat: &ThreatAssessment) -> u32 {
+    let unit_hits = T::parts().len() as u32 * PART_HITS;
+    (threat.dps() * FIGHT_TICKS).div_ceil(unit_hits)
+}
+
+impl DefenderSizing for MeleeDefender {
+    fn units_needed(threat: &ThreatAssessment) -> u32 {
+        // out-damage their healing by half again, and live through their damage doing it
+        let damage = threat.heal * 3 / 2 / ATTACK_POWER + 1;
+        damage.max(units_to_survive::<Self>(threat))
+    }
+}
+
+impl DefenderSizing for RangedDefender {
+    fn units_needed(threat: &ThreatAssessment) -> u32 {
+        let damage = threat.heal * 3 / 2 / RANGED_ATTACK_POWER + 1;
+        damage.max(units_to_survive::<Self>(threat))
+    }
+}
+
+impl DefenderSizing for HealerDefender {
+    fn units_needed(threat: &ThreatAssessment) -> u32 {
+        // enough to heal back what they deal in a tick
+        threat.dps() / HEAL_POWER + 1
+    }
+}
+
+
+/// tries to spawn a defender of the given type, sized to the threat
+pub fn spawn_defender<T: DefenderSizing>(spawn: &StructureSpawn, threat: &ThreatAssessment) -> Result<(), String> {
+    let room = spawn.room();
+    let parts = body::scale(T::parts(), room.energy_available(), T::units_needed(threat));
+    if parts.is_empty() {
+        return Err(format!("not enough energy for {}", T::role()));
+    }
+
+    // set the role & home room of the creep on spawn
+    let mem = memory::MemoryReference::new();
+    mem.set("role", T::role());
+    mem.set("home", room.name().to_string());
+    boosting::request(&mem, T::boosts(), T::boost_policy());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        info!("spawning {} with {} parts in {}", T::role(), parts.len(), room.name());
+        metrics::inc_defenders(1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn {}: {:?}", T::role(), res))
+    }
+}
+
+
+/// runs a defender of any type
+pub fn run_defender(creep: Creep) {
+    trace!("running defender {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let room = creep.room();
+    let threat = ThreatAssessment::assess(&room);
+
+    // the fight is over, give back what we can
+    if !threat.needs_defenders() {
+        creep.say("♻️ Recycle", false);
+        spawning::recycle(&creep);
+        return;
+    }
+
+    let role = creep.memory().string("role").ok().flatten().unwrap_or_default();
+    if role == HealerDefender::role() {
+        run_healer(&creep);
+    } else if role == RangedDefender::role() {
+        run_attacker(&creep, 3);
+    } else {
+        run_attacker(&creep, 1);
+    }
+}
+
+/// Fights the closest hostile, preferring to stand on a rampart within `range` of it
+fn run_attacker(creep: &Creep, range: u32) {
+    let target = match creep.pos().find_closest_by_range(find::HOSTILE_CREEPS) {
+        Some(t) => t,
+        None => return,
+    };
+
+    // move onto the free rampart closest to the target, or straight at the target without one
+    let ramparts: Vec<_> = creep.room().find(find::MY_STRUCTURES).into_iter()
+        .filter(|s| s.structure_type() == StructureType::Rampart)
+        .filter(|s| creep.pos().is_equal_to(s) || s.pos().look_for(look::CREEPS).is_empty())
+        .collect();
+
+    let rampart = ramparts.iter().min_by_key(|r| r.pos().get_range_to(&target));
+    match rampart {
+        Some(r) if r.pos().get_range_to(&target) <= range => {
+            if !creep.pos().is_equal_to(r) {
+                creep.move_to(r);
+            }
+        },
+        _ => {
+            if creep.pos().get_range_to(&target) > range {
+                creep.move_to(&target);
+            }
+        }
+    }
+
+    let r = if range > 1 {
+        creep.ranged_attack(&target)
+    } else {
+        creep.attack(&target)
+    };
+    if r != ReturnCode::Ok && r != ReturnCode::NotInRange {
+        warn!("defender {} couldn't attack: {:?}", creep.name(), r);
+    }
+}
+
+/// Keeps the most damaged friendly creep in the room alive
+fn run_healer(creep: &Creep) {
+    let patient = creep.room().find(find::MY_CREEPS).into_iter()
+        .filter(|c| c.hits() < c.hits_max())
+        .max_by_key(|c| c.hits_max() - c.hits());
+
+    let patient = match patient {
+        Some(p) => p,
+        None => {
+            // stay close to the other defenders until someone needs patching up
+            let buddy = creep.room().find(find::MY_CREEPS).into_iter()
+                .filter(|c| c.name() != creep.name())
+                .find(|c| {
+                    let role = c.memory().string("role").ok().flatten();
+                    role.as_deref() == Some(MeleeDefender::role()) || role.as_deref() == Some(RangedDefender::role())
+                });
+            if let Some(b) = buddy {
+                if !creep.pos().is_near_to(&b) {
+                    creep.move_to(&b);
+                }
+            }
+            return;
+        }
+    };
+
+    if creep.pos().is_near_to(&patient) {
+        creep.heal(&patient);
+    } else {
+        creep.move_to(&patient);
+        if creep.pos().in_range_to(&patient, 3) {
+            creep.ranged_heal(&patient);
+        }
+    }
+}
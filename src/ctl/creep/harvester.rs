@@ -6,48 +6,65 @@ use log::*;
 
 use screeps::prelude::*;
 use screeps::{find};
-use screeps::{Creep, HasStore, ResourceType, ReturnCode, Source, SpawnOptions, StructureSpawn};
+use screeps::{Creep, HasStore, ResourceType, ReturnCode, Source, StructureSpawn};
+use screeps::creep::Part;
 use screeps::memory;
 
+use crate::downgrade;
 use crate::util;
 use crate::metrics;
+use crate::signing;
 use crate::source;
+use super::body;
+use super::spawning;
 use super::types::{BasicHarvester, CreepInfo};
 
 
+/// Cheapest body that can still harvest & deliver, used while a room recovers
+const RECOVERY_PARTS: [Part; 3] = [Part::Work, Part::Carry, Part::Move];
 
-/// tries to spawn a basic harvester
+
+/// tries to spawn a basic harvester, as big as the room's energy allows
 pub fn spawn_basic_harvester(spawn: &StructureSpawn) -> Result<(), String> {
-    if spawn.energy() >= BasicHarvester::cost() {
-        // create a unique name, spawn.
-        let name_base = screeps::game::time();
-        let mut additional = 0;
-
-        // set the role of the creep on spawn
-        let mem = memory::MemoryReference::new();
-        mem.set("role", BasicHarvester::role());
-        let opts = SpawnOptions::new().memory(mem);
-
-        // loop until we get a valid name
-        let res = loop {
-            let name = format!("{}-{}", name_base, additional);
-            let res = spawn.spawn_creep_with_options(&BasicHarvester::parts(), &name, &opts);
-
-            if res == ReturnCode::NameExists {
-                additional += 1;
-            } else {
-                metrics::inc_harvesters(1);
-                break res;
-            }
-        };
+    let room = spawn.room();
+    let parts = body::scale(BasicHarvester::parts(), room.energy_available(), BasicHarvester::max_units());
+    if parts.is_empty() {
+        return Err("not enough energy for basic harvester".to_string());
+    }
 
-        if res != ReturnCode::Ok {
-            warn!("couldn't spawn: {:?}", res);
-        }
+    let mem = memory::MemoryReference::new();
+    mem.set("role", BasicHarvester::role());
+    mem.set("home", room.name().to_string());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        metrics::inc_harvesters(1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn basic harvester: {:?}", res))
+    }
+}
 
+/// tries to spawn a harvester for a room recovering from losing its economy, starting from the
+/// cheapest body that works & growing with whatever energy the room has right now
+pub fn spawn_recovery_harvester(spawn: &StructureSpawn) -> Result<(), String> {
+    let room = spawn.room();
+    let parts = body::scale(&RECOVERY_PARTS, room.energy_available(), BasicHarvester::max_units());
+    if parts.is_empty() {
+        return Err("not enough energy for recovery harvester".to_string());
+    }
+
+    let mem = memory::MemoryReference::new();
+    mem.set("role", BasicHarvester::role());
+    mem.set("home", room.name().to_string());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        info!("spawning recovery harvester with {} parts in room {}", parts.len(), room.name());
+        metrics::inc_harvesters(1);
         Ok(())
     } else {
-        Err("Failed to ".to_string())
+        Err(format!("couldn't spawn recovery harvester: {:?}", res))
     }
 }
 
@@ -105,8 +122,10 @@ pub fn run_basic_harvester(creep: Creep) {
             // give the creep directions
             // store energy in the spawn 25% of the time
             // TODO: adjust this number based on whether or not we already have enough harvesters?
+            // unless the controller's about to downgrade, then it all goes there
             let spawn = &creep.room().find(find::MY_SPAWNS)[0];
-            if spawn.store_of(ResourceType::Energy) < (spawn.store_capacity(Some(ResourceType::Energy))/2) {
+            let at_risk = downgrade::at_risk(&creep.room());
+            if !at_risk && spawn.store_of(ResourceType::Energy) < (spawn.store_capacity(Some(ResourceType::Energy))/2) {
                 if spawn.store_free_capacity(Some(ResourceType::Energy)) > 0 {
                     // FIXME: move to a random or nearest spawn
                     creep.move_to(spawn);
@@ -151,6 +170,9 @@ pub fn run_basic_harvester(creep: Creep) {
         // follow directions to move to and upgrade controller
         if creep.memory().bool("moveToController") {
             if let Some(c) = creep.room().controller() {
+                if signing::sign(&creep, &c) {
+                    return;
+                }
                 let r = creep.upgrade_controller(&c);
                 if r == ReturnCode::NotInRange {
                     creep.move_to(&c);
@@ -0,0 +1,291 @@
+//!
+//! Controls hauler creeps
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Creep, Position, ResourceType, ReturnCode, RoomName, Structure, StructureSpawn};
+use screeps::constants::*;
+use screeps::memory;
+
+use crate::deposit::DepositOp;
+use crate::metrics;
+use crate::remote::{self, RemoteSource};
+use crate::util;
+
+use super::body;
+use super::movement;
+use super::spawning;
+use super::types::{CreepInfo, DepositHarvester, DepositHauler, RemoteHauler};
+
+
+/// tries to spawn a hauler for a source in a remote room
+pub fn spawn_remote_hauler(spawn: &StructureSpawn, source: &RemoteSource, carry: u32) -> Result<(), String> {
+    let room = spawn.room();
+    let parts = body::scale(RemoteHauler::parts(), room.energy_available(), carry);
+    if parts.is_empty() {
+        return Err("not enough energy for remote hauler".to_string());
+    }
+
+    let target = source.pos.room_name().to_string();
+    let mem = memory::MemoryReference::new();
+    mem.set("role", RemoteHauler::role());
+    mem.set("home", room.name().to_string());
+    mem.set("target", target.as_str());
+    mem.set("source", source.id.as_str());
+    mem.set("sourcePos", source.pos.packed_repr());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        metrics::inc_remote_creeps(&target, 1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn remote hauler: {:?}", res))
+    }
+}
+
+/// runs a remote hauler, bringing energy dropped by a remote miner back home
+pub fn run_remote_hauler(creep: Creep) {
+    trace!("running remote hauler {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let pos = match creep.memory().i32("sourcePos").ok().flatten() {
+        Some(p) => Position::from_packed(p),
+        None => {
+            warn!("remote hauler {} has no source", creep.name());
+            spawning::recycle(&creep);
+            return;
+        }
+    };
+
+    // handle change in creep storage space
+    if creep.memory().bool("delivering") {
+        if creep.store_used_capacity(None) == 0 {
+            creep.memory().set("delivering", false);
+        }
+    } else if creep.store_free_capacity(None) == 0 {
+        creep.memory().set("delivering", true);
+    }
+
+    let safe = remote::is_safe(pos.room_name());
+    if !safe && creep.store_used_capacity(None) == 0 {
+        spawning::recycle(&creep);
+        return;
+    }
+
+    if creep.memory().bool("delivering") || !safe {
+        deliver(&creep, &pos.room_name().to_string());
+    } else {
+        collect(&creep, pos);
+    }
+}
+
+/// Picks up energy the miner left around the source
+fn collect(creep: &Creep, source_pos: Position) {
+    if creep.room().name() != source_pos.room_name() || !creep.pos().in_range_to(&source_pos, 2) {
+        movement::travel_to(creep, &source_pos);
+        return;
+    }
+
+    let dropped = source_pos.find_in_range(find::DROPPED_RESOURCES, 2).into_iter()
+        .filter(|r| r.resource_type() == ResourceType::Energy)
+        .max_by_key(|r| r.amount());
+    if let Some(resource) = dropped {
+        if creep.pickup(&resource) == ReturnCode::NotInRange {
+            creep.move_to(&resource);
+        }
+        return;
+    }
+
+    let container = source_pos.find_in_range(find::STRUCTURES, 1).into_iter()
+        .filter_map(|s| match s {
+            Structure::Container(c) => Some(c),
+            _ => None,
+        })
+        .find(|c| c.store_of(ResourceType::Energy) > 0);
+    if let Some(container) = container {
+        if creep.withdraw_all(&container, ResourceType::Energy) == ReturnCode::NotInRange {
+            creep.move_to(&container);
+        }
+    }
+}
+
+/// Brings the energy home, into storage if there is one
+fn deliver(creep: &Creep, remote_name: &str) {
+    let home = match creep.memory().string("home").ok().flatten()
+        .and_then(|h| h.parse::<RoomName>().ok()) {
+        Some(h) => h,
+        None => {
+            warn!("remote hauler {} has no home", creep.name());
+            return;
+        }
+    };
+
+    if creep.room().name() != home {
+        movement::travel_to_room(creep, home);
+        return;
+    }
+
+    let stored = creep.store_used_capacity(Some(ResourceType::Energy));
+    let r = match creep.room().storage() {
+        Some(storage) => {
+            let r = creep.transfer_all(&storage, ResourceType::Energy);
+            if r == ReturnCode::NotInRange {
+                creep.move_to(&storage);
+            }
+            r
+        },
+        None => {
+            let spawn = creep.room().find(find::MY_SPAWNS).into_iter()
+                .filter(|s| s.store_free_capacity(Some(ResourceType::Energy)) > 0)
+                .min_by_key(|s| creep.pos().get_range_to(s));
+            match spawn {
+                Some(spawn) => {
+                    let r = creep.transfer_all(&spawn, ResourceType::Energy);
+                    if r == ReturnCode::NotInRange {
+                        creep.move_to(&spawn);
+                    }
+                    r
+                },
+                None => ReturnCode::Full,
+            }
+        }
+    };
+
+    if r == ReturnCode::Ok {
+        metrics::inc_remote_energy(remote_name, stored);
+    }
+}
+
+
+/// tries to spawn a hauler for a deposit in a highway room
+pub fn spawn_deposit_hauler(spawn: &StructureSpawn, op: &DepositOp) -> Result<(), String> {
+    let room = spawn.room();
+    let parts = body::scale(DepositHauler::parts(), room.energy_available(), MAX_CREEP_SIZE);
+    if parts.is_empty() {
+        return Err("not enough energy for deposit hauler".to_string());
+    }
+
+    let mem = memory::MemoryReference::new();
+    mem.set("role", DepositHauler::role());
+    mem.set("home", room.name().to_string());
+    mem.set("op", op.room.to_string());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        info!("spawning deposit hauler with {} parts for room {}", parts.len(), op.room);
+        metrics::inc_deposit_creeps(1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn deposit hauler: {:?}", res))
+    }
+}
+
+/// runs a deposit hauler, waiting by the deposit's harvesters & bringing what they harvest home
+pub fn run_deposit_hauler(creep: Creep) {
+    trace!("running deposit hauler {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let op = creep.memory().string("op").ok().flatten()
+        .and_then(|r| RoomName::from_str(&r).ok())
+        .and_then(DepositOp::load);
+    let carried = creep.store_used_capacity(None);
+
+    // head home when full, when the operation's over, or while there's still time to make it back
+    let travel = op.as_ref().map(|o| o.travel_ticks()).unwrap_or(0);
+    let leave = creep.store_free_capacity(None) == 0 || op.is_none() || creep.ticks_to_live() < travel + 50;
+    if carried > 0 && (leave || creep.memory().bool("delivering")) {
+        creep.memory().set("delivering", true);
+        deliver_deposit(&creep);
+        return;
+    }
+    creep.memory().set("delivering", false);
+
+    let op = match op {
+        Some(op) if creep.ticks_to_live() > travel * 2 => op,
+        _ => {
+            spawning::recycle(&creep);
+            return;
+        }
+    };
+
+    if creep.room().name() != op.room || !creep.pos().in_range_to(&op.pos, 2) {
+        movement::travel_to(&creep, &op.pos);
+        return;
+    }
+
+    let dropped = op.pos.find_in_range(find::DROPPED_RESOURCES, 2).into_iter()
+        .filter(|r| r.resource_type() == op.resource)
+        .max_by_key(|r| r.amount());
+    if let Some(resource) = dropped {
+        if creep.pickup(&resource) == ReturnCode::NotInRange {
+            creep.move_to(&resource);
+        }
+        return;
+    }
+
+    // wait next to a harvester for it to hand over what it harvests
+    let key = op.room.to_string();
+    let harvester = op.pos.find_in_range(find::MY_CREEPS, 1).into_iter()
+        .filter(|c| c.memory().string("role").ok().flatten().as_deref() == Some(DepositHarvester::role()))
+        .filter(|c| c.memory().string("op").ok().flatten().as_deref() == Some(key.as_str()))
+        .min_by_key(|c| creep.pos().get_range_to(c));
+    if let Some(harvester) = harvester {
+        if !creep.pos().is_near_to(&harvester) {
+            creep.move_to(&harvester);
+        }
+    }
+}
+
+/// Brings deposit resources home, into storage or the terminal
+fn deliver_deposit(creep: &Creep) {
+    let home = match creep.memory().string("home").ok().flatten()
+        .and_then(|h| h.parse::<RoomName>().ok()) {
+        Some(h) => h,
+        None => {
+            warn!("deposit hauler {} has no home", creep.name());
+            return;
+        }
+    };
+
+    if creep.room().name() != home {
+        movement::travel_to_room(creep, home);
+        return;
+    }
+
+    let room = creep.room();
+    // one resource a tick, a creep only gets one transfer
+    if let Some(resource) = creep.store_types().first().copied() {
+        let carried = creep.store_of(resource);
+        let r = match (room.storage(), room.terminal()) {
+            (Some(storage), _) => {
+                let r = creep.transfer_all(&storage, resource);
+                if r == ReturnCode::NotInRange {
+                    creep.move_to(&storage);
+                }
+                r
+            },
+            (None, Some(terminal)) => {
+                let r = creep.transfer_all(&terminal, resource);
+                if r == ReturnCode::NotInRange {
+                    creep.move_to(&terminal);
+                }
+                r
+            },
+            (None, None) => ReturnCode::Full,
+        };
+        if r == ReturnCode::Ok {
+            metrics::inc_deposit_harvested(&util::resource_name(resource), carried);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//!
+//! Replacing creeps on static jobs before they die of old age
+//!
+//! A replacement takes `CREEP_SPAWN_TIME` ticks a body part to spawn, then has to walk to the
+//! job. Each creep records how long its own walk took the first time it gets to work, and
+//! once its ticks to live drop to the time a replacement needs, it's marked as retiring.
+//! Retiring creeps aren't counted when deciding what to spawn, so the replacement gets queued
+//! in time. When the replacement gets to work it relieves its predecessor, which steps off
+//! the job and is recycled. Retiring creeps are listed by name in `Memory.rooms.<home>.retiring`,
+//! so creeps at work only look for a predecessor when their home room has one.
+//!
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{Creep, RoomName};
+use screeps::constants::*;
+use screeps::creep::Part;
+use screeps::memory;
+
+use crate::util::{self, config};
+
+use super::spawning;
+use super::types::{CreepInfo, MineralMiner, RemoteMiner, Reserver, Upgrader};
+
+
+/// Whether a role's creeps hold a job that has to stay staffed
+pub fn is_static(role: &str) -> bool {
+    [RemoteMiner::role(), MineralMiner::role(), Upgrader::role(), Reserver::role()].contains(&role)
+}
+
+/// Ticks a replacement takes to spawn & walk to the job
+pub fn replacement_ticks(body_parts: u32, travel: u32) -> u32 {
+    body_parts * CREEP_SPAWN_TIME + travel
+}
+
+/// Ticks the creep lives from spawning, shorter for creeps with claim parts
+fn lifetime(creep: &Creep) -> u32 {
+    if creep.body().iter().any(|p| p.part == Part::Claim) {
+        CREEP_CLAIM_LIFE_TIME
+    } else {
+        CREEP_LIFE_TIME
+    }
+}
+
+/// Ticks the creep took to get to work, or a guess from the rooms it crossed if it isn't there yet
+fn travel_ticks(creep: &Creep) -> u32 {
+    let mem = creep.memory();
+    if let Some(travel) = mem.i32("travel").ok().flatten() {
+        return travel as u32;
+    }
+    mem.string("home").ok().flatten()
+        .and_then(|h| h.parse::<RoomName>().ok())
+        .map(|home| util::travel_ticks(home, creep.room().name()))
+        .unwrap_or(0)
+}
+
+/// Path to the creep's home room's list of retiring creeps
+fn retiring_path(creep: &Creep) -> Option<String> {
+    let home = creep.memory().string("home").ok().flatten()?;
+    Some(format!("rooms.{}.retiring", home))
+}
+
+/// Whether the creep is about to die & has been replaced, or is being
+pub fn is_retiring(creep: &Creep) -> bool {
+    creep.memory().bool("retiring")
+}
+
+/// Called by static jobs each tick the creep is at work. Records how long the creep took to get
+/// there, and relieves the retiring creep it's replacing, matched by role & the memory key
+/// holding the job's assignment.
+pub fn at_work(creep: &Creep, key: &str) {
+    let mem = creep.memory();
+    if mem.i32("travel").ok().flatten().is_none() {
+        mem.set("travel", lifetime(creep).saturating_sub(creep.ticks_to_live()));
+    }
+    if is_retiring(creep) {
+        return;
+    }
+
+    let path = match retiring_path(creep) {
+        Some(p) => p,
+        None => return,
+    };
+    let names = match memory::root().path_dict(&path).ok().flatten() {
+        Some(retiring) => retiring.keys(),
+        None => return,
+    };
+
+    let role = mem.string("role").ok().flatten();
+    let job = mem.string(key).ok().flatten();
+    for name in names {
+        let other = match screeps::game::creeps::get(&name) {
+            Some(c) if !c.memory().bool("relieved") => c,
+            // dead or already relieved, nothing left to take over
+            _ => {
+                memory::root().path_del(&format!("{}.{}", path, name));
+                continue;
+            }
+        };
+        if other.name() != creep.name()
+            && other.memory().string("role").ok().flatten() == role
+            && other.memory().string(key).ok().flatten() == job {
+            debug!("{} taking over from {}", creep.name(), other.name());
+            other.memory().set("relieved", true);
+            memory::root().path_del(&format!("{}.{}", path, name));
+            return;
+        }
+    }
+}
+
+/// Marks creeps on static jobs as retiring once it's time to spawn their replacement,
+/// and recycles them once relieved. Returns whether the creep is done with its job,
+/// in which case its role shouldn't run.
+pub fn run(creep: &Creep) -> bool {
+    if creep.spawning() {
+        return false;
+    }
+
+    let mem = creep.memory();
+    let role = match mem.string("role").ok().flatten() {
+        Some(r) if is_static(&r) => r,
+        _ => return false,
+    };
+
+    if !is_retiring(creep) {
+        let lead = replacement_ticks(creep.body().len() as u32, travel_ticks(creep))
+            + config::u32_or("lifecycle.margin", 20);
+        if creep.ticks_to_live() <= lead {
+            debug!("{} {} retiring with {} ticks left", role, creep.name(), creep.ticks_to_live());
+            mem.set("retiring", true);
+            if let Some(path) = retiring_path(creep) {
+                memory::root().path_set(&format!("{}.{}", path, creep.name()), true);
+            }
+        }
+        return false;
+    }
+
+    if mem.bool("relieved") {
+        spawning::recycle(creep);
+        return true;
+    }
+    false
+}
@@ -0,0 +1,269 @@
+//!
+//! Controls static miner creeps
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Creep, HasStore, Mineral, Position, ReturnCode, RoomName, Source, StructureSpawn};
+use screeps::constants::*;
+use screeps::memory;
+
+use crate::deposit::DepositOp;
+use crate::metrics;
+use crate::mineral;
+use crate::remote::{self, RemoteSource};
+use crate::util;
+
+use super::body;
+use super::lifecycle;
+use super::movement;
+use super::spawning;
+use super::types::{CreepInfo, DepositHarvester, DepositHauler, MineralMiner, RemoteMiner};
+
+
+/// tries to spawn a miner for a source in a remote room
+pub fn spawn_remote_miner(spawn: &StructureSpawn, source: &RemoteSource) -> Result<(), String> {
+    let room = spawn.room();
+    // five work parts drain a reserved source before it regenerates
+    let parts = body::scale(RemoteMiner::parts(), room.energy_available(), RemoteMiner::max_units());
+    if parts.is_empty() {
+        return Err("not enough energy for remote miner".to_string());
+    }
+
+    let target = source.pos.room_name().to_string();
+    let mem = memory::MemoryReference::new();
+    mem.set("role", RemoteMiner::role());
+    mem.set("home", room.name().to_string());
+    mem.set("target", target.as_str());
+    mem.set("source", source.id.as_str());
+    mem.set("sourcePos", source.pos.packed_repr());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        metrics::inc_remote_creeps(&target, 1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn remote miner: {:?}", res))
+    }
+}
+
+/// runs a remote miner, which sits at its source and drops what it harvests for haulers
+pub fn run_remote_miner(creep: Creep) {
+    trace!("running remote miner {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let pos = match creep.memory().i32("sourcePos").ok().flatten() {
+        Some(p) => Position::from_packed(p),
+        None => {
+            warn!("remote miner {} has no source", creep.name());
+            spawning::recycle(&creep);
+            return;
+        }
+    };
+
+    if !remote::is_safe(pos.room_name()) {
+        spawning::recycle(&creep);
+        return;
+    }
+
+    if creep.room().name() != pos.room_name() {
+        movement::travel_to(&creep, &pos);
+        return;
+    }
+
+    match util::obj_from_mem_id::<Source>(creep.memory(), "source") {
+        Some(source) => {
+            if creep.harvest(&source) == ReturnCode::NotInRange {
+                creep.move_to(&source);
+            } else {
+                lifecycle::at_work(&creep, "source");
+            }
+        },
+        None => {
+            warn!("remote miner {} can't find its source", creep.name());
+            creep.move_to(&pos);
+        }
+    }
+}
+
+
+/// tries to spawn a miner for the room's mineral
+pub fn spawn_mineral_miner(spawn: &StructureSpawn, target: &Mineral) -> Result<(), String> {
+    let room = spawn.room();
+    let parts = body::scale(MineralMiner::parts(), room.energy_available(), MineralMiner::max_units());
+    if parts.is_empty() {
+        return Err("not enough energy for mineral miner".to_string());
+    }
+
+    let mem = memory::MemoryReference::new();
+    mem.set("role", MineralMiner::role());
+    mem.set("home", room.name().to_string());
+    mem.set("mineral", target.id().to_string());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        info!("spawning mineral miner with {} parts in room {}", parts.len(), room.name());
+        metrics::inc_mineral_miners(1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn mineral miner: {:?}", res))
+    }
+}
+
+/// runs a mineral miner, which fills the container next to the mineral,
+/// or carries what it mines to storage when there's no container
+pub fn run_mineral_miner(creep: Creep) {
+    trace!("running mineral miner {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let target = match util::obj_from_mem_id::<Mineral>(creep.memory(), "mineral") {
+        Some(m) => m,
+        None => {
+            warn!("mineral miner {} has no mineral", creep.name());
+            spawning::recycle(&creep);
+            return;
+        }
+    };
+
+    // mineral's run dry or we have plenty
+    if mineral::minable(&creep.room()).is_none() {
+        creep.say("♻️ Recycle", false);
+        spawning::recycle(&creep);
+        return;
+    }
+
+    if let Some(container) = mineral::container(&target) {
+        // harvest from on top of the container, so anything that doesn't fit lands in it.
+        // next to it is close enough to take over, the creep being replaced is still on it
+        if creep.pos().in_range_to(&container, 1) {
+            lifecycle::at_work(&creep, "mineral");
+        }
+        if !creep.pos().is_equal_to(&container) {
+            creep.move_to(&container);
+        } else {
+            creep.harvest(&target);
+        }
+        return;
+    }
+
+    if creep.memory().bool("delivering") {
+        if creep.store_used_capacity(None) == 0 {
+            creep.memory().set("delivering", false);
+        }
+    } else if creep.store_free_capacity(None) == 0 {
+        creep.memory().set("delivering", true);
+    }
+
+    if !creep.memory().bool("delivering") {
+        if creep.harvest(&target) == ReturnCode::NotInRange {
+            creep.move_to(&target);
+        } else {
+            lifecycle::at_work(&creep, "mineral");
+        }
+        return;
+    }
+
+    let resource = target.mineral_type();
+    let room = creep.room();
+    let r = match (room.storage(), room.terminal()) {
+        (Some(storage), _) => {
+            let r = creep.transfer_all(&storage, resource);
+            if r == ReturnCode::NotInRange {
+                creep.move_to(&storage);
+            }
+            r
+        },
+        (None, Some(terminal)) => {
+            let r = creep.transfer_all(&terminal, resource);
+            if r == ReturnCode::NotInRange {
+                creep.move_to(&terminal);
+            }
+            r
+        },
+        (None, None) => {
+            warn!("mineral miner {} has nowhere to put its {}", creep.name(), util::resource_name(resource));
+            return;
+        }
+    };
+    if r != ReturnCode::Ok && r != ReturnCode::NotInRange {
+        warn!("mineral miner {} couldn't deliver: {:?}", creep.name(), r);
+    }
+}
+
+
+/// tries to spawn a harvester for a deposit in a highway room
+pub fn spawn_deposit_harvester(spawn: &StructureSpawn, op: &DepositOp) -> Result<(), String> {
+    let room = spawn.room();
+    let parts = body::scale(DepositHarvester::parts(), room.energy_available(), MAX_CREEP_SIZE);
+    if parts.is_empty() {
+        return Err("not enough energy for deposit harvester".to_string());
+    }
+
+    let mem = memory::MemoryReference::new();
+    mem.set("role", DepositHarvester::role());
+    mem.set("home", room.name().to_string());
+    mem.set("op", op.room.to_string());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        info!("spawning deposit harvester with {} parts for room {}", parts.len(), op.room);
+        metrics::inc_deposit_creeps(1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn deposit harvester: {:?}", res))
+    }
+}
+
+/// runs a deposit harvester, which hands what it harvests to the haulers waiting next to it
+pub fn run_deposit_harvester(creep: Creep) {
+    trace!("running deposit harvester {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let op = match creep.memory().string("op").ok().flatten()
+        .and_then(|r| RoomName::from_str(&r).ok())
+        .and_then(DepositOp::load) {
+        Some(op) => op,
+        None => {
+            creep.say("♻️ Recycle", false);
+            spawning::recycle(&creep);
+            return;
+        }
+    };
+
+    if creep.room().name() != op.room || !creep.pos().is_near_to(&op.pos) {
+        movement::travel_to(&creep, &op.pos);
+        return;
+    }
+
+    let key = op.room.to_string();
+    if creep.store_used_capacity(None) > 0 {
+        let hauler = creep.pos().find_in_range(find::MY_CREEPS, 1).into_iter()
+            .filter(|c| c.memory().string("role").ok().flatten().as_deref() == Some(DepositHauler::role()))
+            .filter(|c| c.memory().string("op").ok().flatten().as_deref() == Some(key.as_str()))
+            .find(|c| c.store_free_capacity(None) > 0);
+        if let Some(hauler) = hauler {
+            creep.transfer_all(&hauler, op.resource);
+        }
+    }
+
+    // anything that doesn't fit is dropped for the haulers to pick up
+    if let Some(deposit) = creep.room().find(find::DEPOSITS).into_iter().find(|d| d.pos() == op.pos) {
+        let r = creep.harvest(&deposit);
+        if r != ReturnCode::Ok && r != ReturnCode::Tired {
+            debug!("deposit harvester {} couldn't harvest: {:?}", creep.name(), r);
+        }
+    }
+}
@@ -2,6 +2,24 @@
 //! Creep control
 //!
 
+pub mod attacker;
+pub mod body;
+pub mod boosting;
 pub mod builder;
+pub mod carrier;
+pub mod claimer;
+pub mod defender;
 pub mod harvester;
+pub mod hauler;
+pub mod lifecycle;
+pub mod miner;
+pub mod movement;
+pub mod operator;
+pub mod pioneer;
+pub mod powerbank;
+pub mod renewal;
+pub mod reserver;
+pub mod scout;
+pub mod spawning;
 pub mod types;
+pub mod upgrader;
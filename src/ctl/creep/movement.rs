@@ -0,0 +1,44 @@
+//!
+//! Moving creeps between rooms, steering clear of rooms the intel marks as dangerous
+//!
+
+use screeps::prelude::*;
+use screeps::{Creep, MoveToOptions, ReturnCode, RoomName};
+use screeps::pathfinder::LocalCostMatrix;
+
+use crate::intel;
+use crate::util;
+
+
+/// Moves the creep toward a position, routing around guarded rooms on the way.
+/// The target's own room is never avoided.
+pub fn travel_to<T: ?Sized + HasPosition>(creep: &Creep, target: &T) -> ReturnCode {
+    let username = creep.owner_name();
+    let target_room = target.pos().room_name();
+
+    let opts = MoveToOptions::new().cost_callback(move |room_name: RoomName, _| {
+        if room_name != target_room && intel::should_avoid(room_name, &username) {
+            Some(blocked().upload())
+        } else {
+            // keep the default costs
+            None
+        }
+    });
+    creep.move_to_with_options(target, opts)
+}
+
+/// Moves the creep toward the center of another room, routing around guarded rooms on the way
+pub fn travel_to_room(creep: &Creep, room_name: RoomName) -> ReturnCode {
+    travel_to(creep, &util::room_center(room_name))
+}
+
+/// A cost matrix that can't be walked through at all
+fn blocked() -> LocalCostMatrix {
+    let mut matrix = LocalCostMatrix::new();
+    for x in 0..50 {
+        for y in 0..50 {
+            matrix.set(x, y, 0xff);
+        }
+    }
+    matrix
+}
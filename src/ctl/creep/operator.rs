@@ -0,0 +1,278 @@
+//!
+//! Controls our power creeps, all of them operators
+//!
+//! Operators are created while the global power level has levels to spare, up to
+//! `powercreeps.max`, and upgraded one power at a time following `powercreeps.build`.
+//! Each is assigned an RCL 8 room with a power spawn, kept in `Memory.powerCreeps.<name>.home`,
+//! where it's spawned & renewed and where it uses its powers: operating the spawns
+//! and extensions and regenerating the sources, with ops it generates itself.
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{PowerType, ResourceType, ReturnCode, Room, RoomName};
+use screeps::memory;
+
+use crate::powerspawn;
+use crate::util::{self, config, PowerCreepState};
+
+
+/// Build followed when `powercreeps.build` isn't set
+const DEFAULT_BUILD: &str = "generate_ops,operate_spawn,operate_extension,regen_source,\
+    generate_ops,operate_extension,regen_source,operate_spawn,generate_ops,operate_extension";
+
+/// Ops an operator keeps on hand, for the most expensive power it uses
+const OPS_RESERVE: u32 = 100;
+
+
+/// Translates a power's name in a build into the power
+fn power_from_name(name: &str) -> Option<PowerType> {
+    match name.trim() {
+        "generate_ops" => Some(PowerType::GenerateOps),
+        "operate_spawn" => Some(PowerType::OperateSpawn),
+        "operate_tower" => Some(PowerType::OperateTower),
+        "operate_storage" => Some(PowerType::OperateStorage),
+        "operate_lab" => Some(PowerType::OperateLab),
+        "operate_extension" => Some(PowerType::OperateExtension),
+        "operate_observer" => Some(PowerType::OperateObserve),
+        "operate_terminal" => Some(PowerType::OperateTerminal),
+        "operate_power" => Some(PowerType::OperatePower),
+        "operate_controller" => Some(PowerType::OperateController),
+        "operate_factory" => Some(PowerType::OperateFactory),
+        "regen_source" => Some(PowerType::RegenSource),
+        "regen_mineral" => Some(PowerType::RegenMineral),
+        "shield" => Some(PowerType::Shield),
+        "fortify" => Some(PowerType::Fortify),
+        _ => None,
+    }
+}
+
+/// Ops a power costs to use, for the powers operators use
+fn ops_cost(power: PowerType) -> u32 {
+    match power {
+        PowerType::OperateSpawn => 100,
+        PowerType::OperateExtension => 2,
+        _ => 0,
+    }
+}
+
+/// Gets the build operators follow: each time a power is listed is one more level of it
+pub fn build() -> Vec<PowerType> {
+    config::string_or("powercreeps.build", DEFAULT_BUILD).split(',')
+        .filter_map(|name| {
+            let power = power_from_name(name);
+            if power.is_none() {
+                warn!("unknown power in powercreeps.build: {}", name);
+            }
+            power
+        })
+        .collect()
+}
+
+/// Picks the next power to upgrade in a build, given the powers the creep has as (power, level, cooldown)
+pub fn next_upgrade(powers: &[(PowerType, u32, u32)], build: &[PowerType]) -> Option<PowerType> {
+    let mut wanted: Vec<(PowerType, u32)> = Vec::new();
+    for power in build {
+        let level = match wanted.iter_mut().find(|(p, _)| p == power) {
+            Some((_, level)) => {
+                *level += 1;
+                *level
+            },
+            None => {
+                wanted.push((*power, 1));
+                1
+            }
+        };
+        let has = powers.iter().find(|(p, _, _)| p == power).map(|(_, l, _)| *l).unwrap_or(0);
+        if has < level {
+            return Some(*power);
+        }
+    }
+    None
+}
+
+/// Gets the room a power creep is assigned to
+fn home_of(name: &str) -> Option<RoomName> {
+    memory::root().path_string(&format!("powerCreeps.{}.home", name)).ok().flatten()
+        .and_then(|h| RoomName::from_str(&h).ok())
+}
+
+/// Assigns a power creep to a room with a power spawn no other operator works, if there is one
+fn assign_home(name: &str, taken: &[RoomName]) -> Option<RoomName> {
+    let home = util::owned_rooms().into_iter()
+        .filter(|r| r.controller().map(|c| c.level()).unwrap_or(0) >= 8)
+        .filter(|r| powerspawn::power_spawn(r).is_some())
+        .map(|r| r.name())
+        .find(|r| !taken.contains(r))?;
+
+    info!("assigning power creep {} to {}", name, home);
+    memory::root().path_set(&format!("powerCreeps.{}.home", name), home.to_string());
+    Some(home)
+}
+
+/// Creates operators & upgrades their powers with spare global power levels,
+/// and finds homes for the ones without
+pub fn manage() {
+    let creeps = util::power_creeps();
+    let mut free = util::gpl_level().saturating_sub(creeps.iter().map(|c| c.level + 1).sum());
+
+    let mut taken: Vec<RoomName> = creeps.iter().filter_map(|c| home_of(&c.name)).collect();
+    for creep in creeps.iter() {
+        if home_of(&creep.name).is_none() {
+            if let Some(home) = assign_home(&creep.name, &taken) {
+                taken.push(home);
+            }
+        }
+    }
+
+    if free > 0 && (creeps.len() as u32) < config::u32_or("powercreeps.max", 1) {
+        let name = format!("operator-{}", screeps::game::time());
+        match util::create_power_creep(&name) {
+            ReturnCode::Ok => {
+                info!("created power creep {}", name);
+                free -= 1;
+            },
+            r => warn!("couldn't create power creep {}: {:?}", name, r),
+        }
+    }
+
+    let build = build();
+    for creep in creeps.iter() {
+        if free == 0 {
+            break;
+        }
+        if let Some(power) = next_upgrade(&creep.powers, &build) {
+            match util::upgrade_power_creep(&creep.name, power) {
+                ReturnCode::Ok => {
+                    info!("upgraded {:?} on power creep {}", power, creep.name);
+                    free -= 1;
+                },
+                // most likely the creep's level isn't high enough for it yet
+                r => debug!("couldn't upgrade {:?} on power creep {}: {:?}", power, creep.name, r),
+            }
+        }
+    }
+}
+
+/// Runs a power creep: spawning & renewing it at its home's power spawn, and using its powers there
+pub fn run(creep: &PowerCreepState) {
+    trace!("running power creep {}", creep.name);
+
+    let home = match home_of(&creep.name).and_then(screeps::game::rooms::get) {
+        Some(h) => h,
+        None => return,
+    };
+    let power_spawn = powerspawn::power_spawn(&home);
+
+    let pos = match creep.pos {
+        Some(p) => p,
+        None => {
+            if let Some(power_spawn) = power_spawn {
+                match util::spawn_power_creep(&creep.name, &power_spawn.id().to_string()) {
+                    ReturnCode::Ok => info!("spawning power creep {} in {}", creep.name, home.name()),
+                    r => debug!("couldn't spawn power creep {}: {:?}", creep.name, r),
+                }
+            }
+            return;
+        }
+    };
+
+    if creep.ticks_to_live < config::u32_or("powercreeps.renew_below", 1_000) {
+        if let Some(power_spawn) = power_spawn {
+            if util::renew_power_creep(&creep.name, &power_spawn.id().to_string()) == ReturnCode::NotInRange {
+                util::move_power_creep(&creep.name, power_spawn.pos());
+            }
+            return;
+        }
+    }
+
+    if pos.room_name() != home.name() {
+        util::move_power_creep(&creep.name, util::room_center(home.name()));
+        return;
+    }
+
+    if let Some(controller) = home.controller() {
+        if !util::power_enabled(&controller) {
+            if util::enable_room(&creep.name, &controller) == ReturnCode::NotInRange {
+                util::move_power_creep(&creep.name, controller.pos());
+            }
+            return;
+        }
+    }
+
+    if manage_ops(creep, &home) {
+        return;
+    }
+    use_powers(creep, &home);
+}
+
+/// Keeps the creep's ops around the reserve, trading with storage.
+/// Returns whether the creep is busy moving to storage.
+fn manage_ops(creep: &PowerCreepState, home: &Room) -> bool {
+    let storage = match home.storage() {
+        Some(s) => s,
+        None => return false,
+    };
+    let id = storage.id().to_string();
+
+    let r = if creep.free_capacity == 0 && creep.ops > OPS_RESERVE {
+        util::power_creep_transfer(&creep.name, &id, ResourceType::Ops, creep.ops - OPS_RESERVE, true)
+    } else if creep.ops < OPS_RESERVE && storage.store_of(ResourceType::Ops) > 0 {
+        let amount = (OPS_RESERVE - creep.ops).min(storage.store_of(ResourceType::Ops)).min(creep.free_capacity);
+        util::power_creep_transfer(&creep.name, &id, ResourceType::Ops, amount, false)
+    } else {
+        return false;
+    };
+
+    if r == ReturnCode::NotInRange {
+        util::move_power_creep(&creep.name, storage.pos());
+        return true;
+    }
+    false
+}
+
+/// Uses the most useful power that's ready, on whatever needs it
+fn use_powers(creep: &PowerCreepState, home: &Room) {
+    let ready = |power: PowerType| creep.powers.iter()
+        .any(|(p, _, cooldown)| *p == power && *cooldown == 0 && creep.ops >= ops_cost(power));
+
+    let mut targets: Vec<(PowerType, Option<(String, screeps::Position)>)> = Vec::new();
+    if ready(PowerType::OperateSpawn) {
+        let spawn = home.find(find::MY_SPAWNS).into_iter()
+            .find(|s| s.is_spawning() && !util::has_effect(&s.id().to_string(), PowerType::OperateSpawn));
+        if let Some(spawn) = spawn {
+            targets.push((PowerType::OperateSpawn, Some((spawn.id().to_string(), spawn.pos()))));
+        }
+    }
+    if ready(PowerType::OperateExtension) && home.energy_available() < home.energy_capacity_available() / 2 {
+        if let Some(storage) = home.storage() {
+            targets.push((PowerType::OperateExtension, Some((storage.id().to_string(), storage.pos()))));
+        }
+    }
+    if ready(PowerType::RegenSource) {
+        let source = home.find(find::SOURCES).into_iter()
+            .find(|s| !util::has_effect(&s.id().to_string(), PowerType::RegenSource));
+        if let Some(source) = source {
+            targets.push((PowerType::RegenSource, Some((source.id().to_string(), source.pos()))));
+        }
+    }
+    if ready(PowerType::GenerateOps) {
+        targets.push((PowerType::GenerateOps, None));
+    }
+
+    // only one power can be used a tick
+    if let Some((power, target)) = targets.into_iter().next() {
+        let r = util::use_power(&creep.name, power, target.as_ref().map(|(id, _)| id.as_str()));
+        match (r, target) {
+            (ReturnCode::Ok, _) => debug!("power creep {} used {:?}", creep.name, power),
+            (ReturnCode::NotInRange, Some((_, pos))) => {
+                util::move_power_creep(&creep.name, pos);
+            },
+            (r, _) => warn!("power creep {} couldn't use {:?}: {:?}", creep.name, power, r),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//!
+//! Controls pioneer creeps, building up a newly claimed room until it has its own spawn
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Creep, HasStore, ResourceType, ReturnCode, RoomName, StructureSpawn, StructureType};
+use screeps::memory;
+
+use crate::metrics;
+
+use super::body;
+use super::movement;
+use super::spawning;
+use super::types::{CreepInfo, Pioneer};
+
+
+/// tries to spawn a pioneer for a newly claimed room
+pub fn spawn_pioneer(spawn: &StructureSpawn, target: RoomName) -> Result<(), String> {
+    let room = spawn.room();
+    let parts = body::scale(Pioneer::parts(), room.energy_available(), 4);
+    if parts.is_empty() {
+        return Err("not enough energy for pioneer".to_string());
+    }
+
+    let mem = memory::MemoryReference::new();
+    mem.set("role", Pioneer::role());
+    mem.set("home", room.name().to_string());
+    mem.set("target", target.to_string());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        info!("spawning pioneer with {} parts for room {}", parts.len(), target);
+        metrics::inc_expansion_creeps(1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn pioneer: {:?}", res))
+    }
+}
+
+/// runs a pioneer, which harvests for itself then fills the spawn, builds, or upgrades
+pub fn run_pioneer(creep: Creep) {
+    trace!("running pioneer {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let target = match creep.memory().string("target").ok().flatten()
+        .and_then(|t| RoomName::from_str(&t).ok()) {
+        Some(t) => t,
+        None => {
+            warn!("pioneer {} has no target room", creep.name());
+            spawning::recycle(&creep);
+            return;
+        }
+    };
+
+    if creep.room().name() != target {
+        movement::travel_to_room(&creep, target);
+        return;
+    }
+
+    if creep.memory().bool("building") {
+        if creep.store_used_capacity(None) == 0 {
+            creep.memory().set("building", false);
+        }
+    } else if creep.store_free_capacity(None) == 0 {
+        creep.memory().set("building", true);
+    }
+
+    if creep.memory().bool("building") {
+        work(&creep);
+    } else {
+        match creep.pos().find_closest_by_range(find::SOURCES_ACTIVE) {
+            Some(source) => {
+                if creep.harvest(&source) == ReturnCode::NotInRange {
+                    creep.move_to(&source);
+                }
+            },
+            None => {
+                // sources are dry, put whatever's carried to use
+                if creep.store_used_capacity(None) > 0 {
+                    creep.memory().set("building", true);
+                }
+            }
+        }
+    }
+}
+
+/// Spends carried energy: keep the spawn topped up, then build, spawn sites first, then upgrade
+fn work(creep: &Creep) {
+    let room = creep.room();
+
+    let spawn = room.find(find::MY_SPAWNS).into_iter()
+        .find(|s| s.store_free_capacity(Some(ResourceType::Energy)) > 0);
+    if let Some(spawn) = spawn {
+        if creep.transfer_all(&spawn, ResourceType::Energy) == ReturnCode::NotInRange {
+            creep.move_to(&spawn);
+        }
+        return;
+    }
+
+    let mut sites = room.find(find::MY_CONSTRUCTION_SITES);
+    sites.sort_by_key(|s| (s.structure_type() != StructureType::Spawn, creep.pos().get_range_to(s)));
+    if let Some(site) = sites.first() {
+        if creep.build(site) == ReturnCode::NotInRange {
+            creep.move_to(site);
+        }
+        return;
+    }
+
+    if let Some(controller) = room.controller() {
+        if creep.upgrade_controller(&controller) == ReturnCode::NotInRange {
+            creep.move_to(&controller);
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//!
+//! Controls the creeps breaking power banks & bringing the power home
+//!
+//! Each attacker gets a healer as its partner. Attackers wait for their healer to be next to
+//! them before the first hit, and from then on only keep hitting the bank while it's there or
+//! they're in good health, since the bank hits back.
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Attackable, Creep, Position, ResourceType, ReturnCode, RoomName, Structure, StructureSpawn};
+use screeps::memory;
+
+use crate::metrics;
+use crate::power::{self, PowerOp, PowerStage};
+
+use super::body;
+use super::movement;
+use super::spawning;
+use super::types::{CreepInfo, PowerAttacker, PowerHauler, PowerHealer};
+
+
+/// Range haulers wait at while the bank is still standing, out of the way of the duos
+const WAIT_RANGE: u32 = 3;
+
+
+/// Spawns a creep for a power operation, holding off until the room can afford the full body
+fn spawn_for_op(spawn: &StructureSpawn, role: &str, unit: &[screeps::Part], units: u32, op: &PowerOp) -> Result<(), String> {
+    let room = spawn.room();
+    if room.energy_available() < body::cost(unit) * units {
+        return Err(format!("not enough energy for {}", role));
+    }
+    let parts = body::scale(unit, room.energy_available(), units);
+
+    let mem = memory::MemoryReference::new();
+    mem.set("role", role);
+    mem.set("home", room.name().to_string());
+    mem.set("op", op.room.to_string());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        info!("spawning {} with {} parts for power bank in {}", role, parts.len(), op.room);
+        metrics::inc_power_creeps(1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn {}: {:?}", role, res))
+    }
+}
+
+/// tries to spawn an attacker for a power operation
+pub fn spawn_power_attacker(spawn: &StructureSpawn, op: &PowerOp) -> Result<(), String> {
+    spawn_for_op(spawn, PowerAttacker::role(), PowerAttacker::parts(), op.attack_parts, op)
+}
+
+/// tries to spawn a healer for a power operation
+pub fn spawn_power_healer(spawn: &StructureSpawn, op: &PowerOp) -> Result<(), String> {
+    spawn_for_op(spawn, PowerHealer::role(), PowerHealer::parts(), op.heal_parts, op)
+}
+
+/// tries to spawn a hauler for a power operation
+pub fn spawn_power_hauler(spawn: &StructureSpawn, op: &PowerOp) -> Result<(), String> {
+    spawn_for_op(spawn, PowerHauler::role(), PowerHauler::parts(), op.carry_parts, op)
+}
+
+
+/// Gets the operation a creep works for, if it's still running
+fn op_of(creep: &Creep) -> Option<PowerOp> {
+    creep.memory().string("op").ok().flatten()
+        .and_then(|r| RoomName::from_str(&r).ok())
+        .and_then(PowerOp::load)
+}
+
+/// Gets a creep's partner, if it's still alive
+fn partner_of(creep: &Creep) -> Option<Creep> {
+    creep.memory().string("partner").ok().flatten()
+        .and_then(|name| screeps::game::creeps::get(&name))
+}
+
+/// Finds the power bank at a position, if it's visible & still standing
+fn bank_at(pos: Position) -> Option<screeps::StructurePowerBank> {
+    pos.look_for(screeps::look::STRUCTURES).into_iter()
+        .find_map(|s| match s {
+            Structure::PowerBank(b) => Some(b),
+            _ => None,
+        })
+}
+
+
+/// runs a power bank attacker
+pub fn run_power_attacker(creep: Creep) {
+    trace!("running power attacker {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let op = match op_of(&creep) {
+        Some(op) if op.stage == PowerStage::Attacking => op,
+        _ => {
+            spawning::recycle(&creep);
+            return;
+        }
+    };
+
+    if creep.room().name() != op.room {
+        movement::travel_to(&creep, &op.pos);
+        return;
+    }
+    let bank = match bank_at(op.pos) {
+        Some(b) => b,
+        None => return,
+    };
+
+    // the bank hits back, so only start once the healer's alongside & only keep at it while it's safe
+    let mem = creep.memory();
+    let healed = partner_of(&creep).map(|h| h.pos().is_near_to(&creep)).unwrap_or(false);
+    if !healed && (!mem.bool("engaged") || creep.hits() < creep.hits_max() / 2) {
+        if !creep.pos().is_near_to(&bank) {
+            movement::travel_to(&creep, &bank);
+        }
+        return;
+    }
+
+    match creep.attack(&bank) {
+        ReturnCode::Ok => mem.set("engaged", true),
+        ReturnCode::NotInRange => {
+            movement::travel_to(&creep, &bank);
+        },
+        _ => (),
+    }
+}
+
+/// runs a power bank healer, keeping its partner attacker alive
+pub fn run_power_healer(creep: Creep) {
+    trace!("running power healer {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let op = match op_of(&creep) {
+        Some(op) if op.stage == PowerStage::Attacking => op,
+        _ => {
+            spawning::recycle(&creep);
+            return;
+        }
+    };
+
+    let partner = match partner_of(&creep) {
+        Some(p) => p,
+        None => match find_partner(&creep, &op) {
+            Some(p) => p,
+            None => {
+                // every attacker has a healer already, wait by the bank
+                if creep.room().name() != op.room || !creep.pos().in_range_to(&op.pos, WAIT_RANGE) {
+                    movement::travel_to(&creep, &op.pos);
+                }
+                return;
+            }
+        }
+    };
+
+    if !creep.pos().is_near_to(&partner) {
+        movement::travel_to(&creep, &partner);
+    }
+
+    if partner.hits() < partner.hits_max() {
+        if creep.heal(&partner) == ReturnCode::NotInRange {
+            creep.ranged_heal(&partner);
+        }
+    } else if creep.hits() < creep.hits_max() {
+        creep.heal(&creep);
+    }
+}
+
+/// Pairs a healer with an attacker of its operation that's missing a healer
+fn find_partner(creep: &Creep, op: &PowerOp) -> Option<Creep> {
+    let key = op.room.to_string();
+    let attacker = screeps::game::creeps::values().into_iter()
+        .filter(|c| c.memory().string("role").ok().flatten().as_deref() == Some(PowerAttacker::role()))
+        .filter(|c| c.memory().string("op").ok().flatten().as_deref() == Some(key.as_str()))
+        .find(|c| partner_of(c).is_none())?;
+
+    debug!("pairing power healer {} with attacker {}", creep.name(), attacker.name());
+    attacker.memory().set("partner", creep.name());
+    creep.memory().set("partner", attacker.name());
+    Some(attacker)
+}
+
+/// runs a power hauler, picking up the power once the bank breaks & bringing it home
+pub fn run_power_hauler(creep: Creep) {
+    trace!("running power hauler {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    if creep.store_used_capacity(Some(ResourceType::Power)) > 0 {
+        let full = creep.store_free_capacity(None) == 0;
+        let done = op_of(&creep).map(|op| op.stage == PowerStage::Hauling
+            && screeps::game::rooms::get(op.room).map(|r| power::power_left(&r) == 0).unwrap_or(false));
+        if full || done.unwrap_or(true) {
+            deliver(&creep);
+            return;
+        }
+    }
+
+    let op = match op_of(&creep) {
+        Some(op) => op,
+        None => {
+            spawning::recycle(&creep);
+            return;
+        }
+    };
+
+    if creep.room().name() != op.room || !creep.pos().in_range_to(&op.pos, WAIT_RANGE) {
+        movement::travel_to(&creep, &op.pos);
+        return;
+    }
+    if op.stage == PowerStage::Attacking {
+        return;
+    }
+
+    let dropped = creep.room().find(find::DROPPED_RESOURCES).into_iter()
+        .filter(|r| r.resource_type() == ResourceType::Power)
+        .min_by_key(|r| creep.pos().get_range_to(r));
+    if let Some(resource) = dropped {
+        if creep.pickup(&resource) == ReturnCode::NotInRange {
+            movement::travel_to(&creep, &resource);
+        }
+        return;
+    }
+
+    let ruin = creep.room().find(find::RUINS).into_iter()
+        .filter(|r| r.store_of(ResourceType::Power) > 0)
+        .min_by_key(|r| creep.pos().get_range_to(r));
+    if let Some(ruin) = ruin {
+        if creep.withdraw_all(&ruin, ResourceType::Power) == ReturnCode::NotInRange {
+            movement::travel_to(&creep, &ruin);
+        }
+    }
+}
+
+/// Brings the power home, into storage or the terminal
+fn deliver(creep: &Creep) {
+    let home = match creep.memory().string("home").ok().flatten()
+        .and_then(|h| h.parse::<RoomName>().ok()) {
+        Some(h) => h,
+        None => {
+            warn!("power hauler {} has no home", creep.name());
+            return;
+        }
+    };
+
+    if creep.room().name() != home {
+        movement::travel_to_room(creep, home);
+        return;
+    }
+
+    let room = creep.room();
+    let carried = creep.store_used_capacity(Some(ResourceType::Power));
+    let r = match room.storage().filter(|s| s.store_free_capacity(None) > 0) {
+        Some(storage) => {
+            let r = creep.transfer_all(&storage, ResourceType::Power);
+            if r == ReturnCode::NotInRange {
+                creep.move_to(&storage);
+            }
+            r
+        },
+        None => match room.terminal() {
+            Some(terminal) => {
+                let r = creep.transfer_all(&terminal, ResourceType::Power);
+                if r == ReturnCode::NotInRange {
+                    creep.move_to(&terminal);
+                }
+                r
+            },
+            None => ReturnCode::Full,
+        }
+    };
+
+    if r == ReturnCode::Ok {
+        metrics::inc_power_harvested(carried);
+    }
+}
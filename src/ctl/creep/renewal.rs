@@ -0,0 +1,171 @@
+//!
+//! Renewing & recycling creeps that work in their home room
+//!
+//! A creep whose body is about as big as the room can build today is worth keeping: once its
+//! ticks to live run low it heads to an idle spawn & is renewed, which costs less than spawning
+//! it again. A creep that's much smaller than what the room can now afford, e.g. one spawned
+//! before the extensions were built, is recycled instead, one at a time and only while there's
+//! the energy for a full sized replacement, so the room isn't left short.
+//!
+//! Creeps on static jobs are left to `lifecycle`, which replaces them ahead of time, and
+//! boosted creeps are never renewed since renewing strips their boosts.
+//!
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Creep, ReturnCode, Room, RoomName};
+use screeps::creep::Part;
+
+use crate::util::config;
+
+use super::body;
+use super::spawning;
+use super::types::{BasicBuilder, BasicHarvester, Carrier, CreepInfo};
+
+
+/// Gets the unit & most units a home room role's bodies are scaled with
+fn scaling(role: &str) -> Option<(&'static [Part], u32)> {
+    if role == BasicHarvester::role() {
+        Some((BasicHarvester::parts(), BasicHarvester::max_units()))
+    } else if role == BasicBuilder::role() {
+        Some((BasicBuilder::parts(), BasicBuilder::max_units()))
+    } else if role == Carrier::role() {
+        Some((Carrier::parts(), Carrier::max_units()))
+    } else {
+        None
+    }
+}
+
+/// Cost of the biggest body the room could spawn for a role, if the role scales
+pub fn optimal_cost(role: &str, room: &Room) -> Option<u32> {
+    let (unit, max_units) = scaling(role)?;
+    Some(body::cost(&body::scale(unit, room.energy_capacity_available(), max_units)))
+}
+
+/// Energy the creep's body cost to spawn
+fn body_cost(creep: &Creep) -> u32 {
+    creep.body().iter().fold(0, |cost, p| cost + p.part.cost())
+}
+
+/// Whether the creep is on its way to, or at, a spawn being renewed
+pub fn is_renewing(creep: &Creep) -> bool {
+    creep.memory().bool("renewing")
+}
+
+/// Whether the creep is on its way to be recycled
+fn is_recycling(creep: &Creep) -> bool {
+    creep.memory().bool("recycling")
+}
+
+/// Creeps of a role in the room still on the job
+fn working(room: &Room, role: &str) -> u32 {
+    room.find(find::MY_CREEPS).iter()
+        .filter(|c| !is_recycling(c) && !c.memory().bool("retiring"))
+        .filter(|c| c.memory().string("role").ok().flatten().as_deref() == Some(role))
+        .count() as u32
+}
+
+/// Whether the room can spare a creep of the given role to recycle right now
+fn can_recycle(room: &Room, role: &str, optimal: u32) -> bool {
+    room.energy_available() >= optimal
+        && working(room, role) >= 2
+        && !room.find(find::MY_CREEPS).iter().any(is_recycling)
+}
+
+/// Renews the creep at the nearest spawn, returning whether it's done
+fn renew(creep: &Creep, room: &Room) -> bool {
+    if creep.ticks_to_live() >= config::u32_or("renew.until", 1200) {
+        return true;
+    }
+
+    let spawn = room.find(find::MY_SPAWNS).into_iter()
+        .filter(|s| s.spawning().is_none())
+        .min_by_key(|s| creep.pos().get_range_to(s));
+    let spawn = match spawn {
+        Some(s) => s,
+        // every spawn's busy, back to work until one frees up
+        None => return true,
+    };
+
+    match spawn.renew_creep(creep) {
+        ReturnCode::Ok => false,
+        ReturnCode::NotInRange => {
+            creep.move_to(&spawn);
+            false
+        },
+        ReturnCode::Full | ReturnCode::NotEnough => true,
+        r => {
+            warn!("couldn't renew creep {}: {:?}", creep.name(), r);
+            true
+        }
+    }
+}
+
+/// Sends creeps working in their home room to a spawn to be renewed when they're worth keeping,
+/// or recycled when they're obsolete. Returns whether the creep is busy at the spawn, in which
+/// case its role shouldn't run.
+pub fn run(creep: &Creep) -> bool {
+    if creep.spawning() {
+        return false;
+    }
+
+    let mem = creep.memory();
+    if is_recycling(creep) {
+        spawning::recycle(creep);
+        return true;
+    }
+
+    if !config::bool_or("renew.enabled", true) {
+        return false;
+    }
+
+    let room = creep.room();
+    let home = mem.string("home").ok().flatten()
+        .and_then(|h| h.parse::<RoomName>().ok())
+        .unwrap_or_else(|| room.name());
+    if home != room.name() || !room.controller().map(|c| c.my()).unwrap_or(false) {
+        return false;
+    }
+
+    let role = match mem.string("role").ok().flatten() {
+        Some(r) => r,
+        None => return false,
+    };
+    let optimal = match optimal_cost(&role, &room) {
+        Some(c) if c > 0 => c,
+        _ => return false,
+    };
+
+    if is_renewing(creep) {
+        if renew(creep, &room) {
+            mem.del("renewing");
+            return false;
+        }
+        return true;
+    }
+
+    let cost = body_cost(creep);
+    if cost * 100 < optimal * config::u32_or("renew.obsolete_percent", 50) {
+        if can_recycle(&room, &role, optimal) {
+            info!("recycling obsolete {} {}, {} of {} energy", role, creep.name(), cost, optimal);
+            // not counted from here on, so a full sized replacement gets spawned
+            mem.set("recycling", true);
+            mem.set("retiring", true);
+            spawning::recycle(creep);
+            return true;
+        }
+        return false;
+    }
+
+    let boosted = creep.body().iter().any(|p| p.boost.is_some());
+    let worth_renewing = cost * 100 >= optimal * config::u32_or("renew.optimal_percent", 90) && !boosted;
+    if worth_renewing
+        && creep.ticks_to_live() < config::u32_or("renew.below", 300)
+        && room.find(find::MY_SPAWNS).iter().any(|s| s.spawning().is_none()) {
+        debug!("renewing {} {} with {} ticks left", role, creep.name(), creep.ticks_to_live());
+        mem.set("renewing", true);
+    }
+    false
+}
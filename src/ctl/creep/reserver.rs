@@ -0,0 +1,93 @@
+//!
+//! Controls reserver creeps, keeping remote room controllers reserved
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{Creep, ReturnCode, RoomName, StructureSpawn};
+use screeps::memory;
+
+use crate::metrics;
+use crate::remote;
+use crate::signing;
+
+use super::body;
+use super::lifecycle;
+use super::movement;
+use super::spawning;
+use super::types::{CreepInfo, Reserver};
+
+
+/// tries to spawn a reserver for a remote room
+pub fn spawn_reserver(spawn: &StructureSpawn, target: RoomName) -> Result<(), String> {
+    let room = spawn.room();
+    let parts = body::scale(Reserver::parts(), room.energy_available(), Reserver::max_units());
+    if parts.is_empty() {
+        return Err("not enough energy for reserver".to_string());
+    }
+
+    let mem = memory::MemoryReference::new();
+    mem.set("role", Reserver::role());
+    mem.set("home", room.name().to_string());
+    mem.set("target", target.to_string());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        metrics::inc_remote_creeps(&target.to_string(), 1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn reserver: {:?}", res))
+    }
+}
+
+/// runs a reserver
+pub fn run_reserver(creep: Creep) {
+    trace!("running reserver {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let target = match creep.memory().string("target").ok().flatten()
+        .and_then(|t| RoomName::from_str(&t).ok()) {
+        Some(t) => t,
+        None => {
+            warn!("reserver {} has no target room", creep.name());
+            spawning::recycle(&creep);
+            return;
+        }
+    };
+
+    if !remote::is_safe(target) {
+        spawning::recycle(&creep);
+        return;
+    }
+
+    if creep.room().name() != target {
+        movement::travel_to_room(&creep, target);
+        return;
+    }
+
+    match creep.room().controller() {
+        Some(controller) => {
+            if signing::sign(&creep, &controller) {
+                return;
+            }
+            let r = creep.reserve_controller(&controller);
+            if r == ReturnCode::NotInRange {
+                creep.move_to(&controller);
+            } else if r == ReturnCode::Ok {
+                lifecycle::at_work(&creep, "target");
+            } else {
+                warn!("reserver {} couldn't reserve {}: {:?}", creep.name(), target, r);
+            }
+        },
+        None => {
+            warn!("remote room {} has no controller to reserve", target);
+            spawning::recycle(&creep);
+        }
+    }
+}
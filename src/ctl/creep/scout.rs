@@ -0,0 +1,90 @@
+//!
+//! Controls scout creeps, visiting the rooms around ours to keep their intel fresh
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{Creep, ReturnCode, RoomName, StructureSpawn};
+use screeps::memory;
+
+use crate::intel;
+use crate::metrics;
+use crate::util::{self, config};
+
+use super::movement;
+use super::spawning;
+use super::types::{CreepInfo, Scout};
+
+
+/// tries to spawn a scout
+pub fn spawn_scout(spawn: &StructureSpawn) -> Result<(), String> {
+    let room = spawn.room();
+    if room.energy_available() < Scout::cost() {
+        return Err("not enough energy for scout".to_string());
+    }
+
+    let mem = memory::MemoryReference::new();
+    mem.set("role", Scout::role());
+    mem.set("home", room.name().to_string());
+
+    let res = spawning::spawn_with_memory(spawn, Scout::parts(), mem);
+    if res == ReturnCode::Ok {
+        metrics::inc_scouts(1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn scout: {:?}", res))
+    }
+}
+
+/// runs a scout, heading for stale rooms one after another.
+/// The room's intel gets recorded once the scout gives us vision of it.
+pub fn run_scout(creep: Creep) {
+    trace!("running scout {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    let target = creep.memory().string("target").ok().flatten()
+        .and_then(|t| RoomName::from_str(&t).ok())
+        .filter(|t| *t != creep.room().name());
+
+    let target = match target {
+        Some(t) => t,
+        None => match next_target(&creep) {
+            Some(t) => {
+                creep.memory().set("target", t.to_string());
+                creep.memory().set("since", screeps::game::time());
+                t
+            },
+            None => {
+                // nothing left to look at
+                spawning::recycle(&creep);
+                return;
+            }
+        },
+    };
+
+    let since = creep.memory().i32("since").ok().flatten().unwrap_or(0) as u32;
+    let travel = screeps::game::time() - since;
+    if travel > config::u32_or("scout.max_travel", 1_000)
+        || movement::travel_to_room(&creep, target) == ReturnCode::NoPath {
+        debug!("scout {} couldn't reach {}, skipping it", creep.name(), target);
+        intel::skip(target);
+        creep.memory().del("target");
+    }
+}
+
+/// Picks the closest stale room that no other scout is heading for
+fn next_target(creep: &Creep) -> Option<RoomName> {
+    let home = creep.memory().string("home").ok().flatten()
+        .and_then(|h| RoomName::from_str(&h).ok())?;
+    let here = creep.room().name();
+
+    intel::stale_rooms(home).into_iter()
+        .filter(|r| util::count_creeps(Scout::role(), "target", &r.to_string()) == 0)
+        .min_by_key(|r| screeps::game::map::get_room_linear_distance(here, *r, false))
+}
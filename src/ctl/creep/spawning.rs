@@ -0,0 +1,131 @@
+//!
+//! Shared helpers for creating & disposing of creeps at spawns
+//!
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find, look};
+use screeps::{Creep, Direction, Position, ReturnCode, RoomName, SpawnOptions, StructureSpawn, StructureType, Terrain};
+use screeps::creep::Part;
+use screeps::memory::MemoryReference;
+
+use super::movement;
+
+
+/// Directions around a spawn, with the offset each one steps
+const DIRECTIONS: [(Direction, i32, i32); 8] = [
+    (Direction::Top, 0, -1),
+    (Direction::TopRight, 1, -1),
+    (Direction::Right, 1, 0),
+    (Direction::BottomRight, 1, 1),
+    (Direction::Bottom, 0, 1),
+    (Direction::BottomLeft, -1, 1),
+    (Direction::Left, -1, 0),
+    (Direction::TopLeft, -1, -1),
+];
+
+
+/// Whether creeps can stand on a tile: not a wall, and nothing built on it but roads,
+/// containers & ramparts
+fn walkable(pos: Position) -> bool {
+    let terrain = screeps::game::map::get_room_terrain(pos.room_name());
+    terrain.get(pos.x(), pos.y()) != Terrain::Wall
+        && pos.look_for(look::STRUCTURES).iter().all(|s| matches!(
+            s.structure_type(),
+            StructureType::Road | StructureType::Container | StructureType::Rampart
+        ))
+}
+
+/// Number of tiles around a position that aren't walls, leaving out one
+fn open_neighbours(pos: Position, except: Position) -> usize {
+    let terrain = screeps::game::map::get_room_terrain(pos.room_name());
+    DIRECTIONS.iter()
+        .map(|(_, dx, dy)| pos + (*dx, *dy))
+        .filter(|p| *p != except && (1..49).contains(&p.x()) && (1..49).contains(&p.y()))
+        .filter(|p| terrain.get(p.x(), p.y()) != Terrain::Wall)
+        .count()
+}
+
+/// Directions a new creep may leave the spawn in, best first. Tiles with the most room to move
+/// on from come first, so creeps don't pile up in the spawn's exit. The filler's tile, next to
+/// the most extensions where a carrier stands topping them & the spawn up, comes last.
+/// Tiles creeps can't stand on are left out.
+pub fn directions(spawn: &StructureSpawn) -> Vec<Direction> {
+    let pos = spawn.pos();
+    let extensions: Vec<Position> = pos.find_in_range(find::MY_STRUCTURES, 2).into_iter()
+        .filter(|s| s.structure_type() == StructureType::Extension)
+        .map(|s| s.pos())
+        .collect();
+
+    let mut open: Vec<(Direction, Position)> = DIRECTIONS.iter()
+        .map(|(d, dx, dy)| (*d, pos + (*dx, *dy)))
+        .filter(|(_, p)| walkable(*p))
+        .collect();
+    let filler = open.iter()
+        .map(|(d, p)| (*d, extensions.iter().filter(|e| e.in_range_to(p, 1)).count()))
+        .filter(|(_, near)| *near > 0)
+        .max_by_key(|(_, near)| *near)
+        .map(|(d, _)| d);
+
+    open.sort_by_key(|(d, p)| (Some(*d) == filler, std::cmp::Reverse(open_neighbours(*p, pos))));
+    open.into_iter().map(|(d, _)| d).collect()
+}
+
+/// Spawns a creep with the given body and memory, picking a unique name.
+/// Creeps record the room they're spawned for as their home, the spawn's room unless set already.
+pub fn spawn_with_memory(spawn: &StructureSpawn, parts: &[Part], mem: MemoryReference) -> ReturnCode {
+    if mem.string("home").ok().flatten().is_none() {
+        mem.set("home", spawn.room().name().to_string());
+    }
+
+    // create a unique name, spawn.
+    let name_base = screeps::game::time();
+    let mut additional = 0;
+
+    let mut opts = SpawnOptions::new().memory(mem);
+    let directions = directions(spawn);
+    if !directions.is_empty() {
+        opts = opts.directions(&directions);
+    }
+
+    // loop until we get a valid name
+    loop {
+        let name = format!("{}-{}", name_base, additional);
+        let res = spawn.spawn_creep_with_options(parts, &name, &opts);
+
+        if res == ReturnCode::NameExists {
+            additional += 1;
+        } else {
+            break res;
+        }
+    }
+}
+
+/// Walks the creep to the closest spawn in its room and recycles it there,
+/// recovering some of the energy spent on it. Creeps in a room without a spawn head home first.
+pub fn recycle(creep: &Creep) {
+    let home = creep.memory().string("home").ok().flatten()
+        .and_then(|h| h.parse::<RoomName>().ok());
+
+    match creep.pos().find_closest_by_range(find::MY_SPAWNS) {
+        Some(spawn) => {
+            let r = spawn.recycle_creep(creep);
+            if r == ReturnCode::NotInRange {
+                creep.move_to(&spawn);
+            } else if r != ReturnCode::Ok {
+                warn!("couldn't recycle creep {}: {:?}", creep.name(), r);
+            }
+        },
+        None => match home {
+            Some(home) if home != creep.room().name() => {
+                movement::travel_to_room(creep, home);
+            },
+            _ => {
+                // nowhere to recycle, don't leave it wandering around
+                debug!("no spawn to recycle creep {}, suiciding", creep.name());
+                creep.suicide();
+            }
+        }
+    }
+}
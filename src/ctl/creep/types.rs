@@ -2,18 +2,30 @@
 //! 
 //!
 
+use screeps::ResourceType;
 use screeps::creep::Part;
 
+use super::body;
+
 
 /// High level creep types
 pub enum CreepType {
     Harvester(HarvesterType),
     Builder(BuilderType),
+    Defender(DefenderType),
+    Attacker(AttackerType),
+    Remote(RemoteType),
+    Expansion(ExpansionType),
+    Scout(Scout),
+    Logistics(LogisticsType),
+    Power(PowerType),
+    Deposit(DepositType),
 }
 
 /// Types of harvester creeps
 pub enum HarvesterType {
     BasicHarvester(BasicHarvester),
+    MineralMiner(MineralMiner),
 }
 
 /// Types of builder creeps
@@ -21,6 +33,59 @@ pub enum BuilderType {
     BasicBuilder(BasicBuilder),
 }
 
+/// Types of defender creeps
+pub enum DefenderType {
+    MeleeDefender(MeleeDefender),
+    RangedDefender(RangedDefender),
+    HealerDefender(HealerDefender),
+}
+
+/// Types of attacker creeps
+pub enum AttackerType {
+    CoreAttacker(CoreAttacker),
+}
+
+/// Types of creeps working remote rooms
+pub enum RemoteType {
+    Reserver(Reserver),
+    RemoteMiner(RemoteMiner),
+    RemoteHauler(RemoteHauler),
+}
+
+/// Types of creeps moving resources around & spending them inside a room
+pub enum LogisticsType {
+    Carrier(Carrier),
+    Upgrader(Upgrader),
+}
+
+/// Types of creeps breaking power banks & bringing the power home
+pub enum PowerType {
+    PowerAttacker(PowerAttacker),
+    PowerHealer(PowerHealer),
+    PowerHauler(PowerHauler),
+}
+
+/// Types of creeps harvesting deposits in highway rooms
+pub enum DepositType {
+    DepositHarvester(DepositHarvester),
+    DepositHauler(DepositHauler),
+}
+
+/// Types of creeps claiming & building up new rooms
+pub enum ExpansionType {
+    Claimer(Claimer),
+    Pioneer(Pioneer),
+}
+
+/// What a creep does about a boost its room's labs can't give it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoostPolicy {
+    /// go without it
+    Skip,
+    /// wait at the labs for it, up to `boost.max_wait` ticks
+    Wait,
+}
+
 /// Get info for a creep type
 pub trait CreepInfo {
     /// Returns a JSON friendly role name
@@ -31,6 +96,21 @@ pub trait CreepInfo {
 
     /// get the cost associated with the creep parts
     fn cost() -> u32;
+
+    /// Gets the boosts a creep picks up from the labs before starting its job
+    fn boosts() -> &'static [ResourceType] {
+        &[]
+    }
+
+    /// Gets what a creep does when one of its boosts isn't available
+    fn boost_policy() -> BoostPolicy {
+        BoostPolicy::Skip
+    }
+
+    /// Gets the most times the parts are repeated in a creep's body, for roles that scale
+    fn max_units() -> u32 {
+        1
+    }
 }
 
 
@@ -57,6 +137,10 @@ impl CreepInfo for BasicHarvester {
             BASIC_HARVESTER_COST.unwrap()
         }
     }
+
+    fn max_units() -> u32 {
+        4
+    }
 }
 
 
@@ -83,4 +167,427 @@ impl CreepInfo for BasicBuilder {
             BASIC_BUILDER_COST.unwrap()
         }
     }
-}
\ No newline at end of file
+
+    fn max_units() -> u32 {
+        4
+    }
+}
+
+
+/// Information for creating and using a melee defender.
+/// Parts are a single unit of the body, which gets repeated to match the threat.
+pub struct MeleeDefender {}
+
+static MELEE_DEFENDER_PARTS: [Part; 3] = [Part::Tough, Part::Attack, Part::Move];
+
+impl CreepInfo for MeleeDefender {
+    fn role() -> &'static str {
+        "melee_defender"
+    }
+
+    fn parts() -> &'static [Part] {
+        &MELEE_DEFENDER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&MELEE_DEFENDER_PARTS)
+    }
+
+    fn boosts() -> &'static [ResourceType] {
+        &[ResourceType::CatalyzedUtriumAcid]
+    }
+}
+
+
+/// Information for creating and using a ranged defender.
+/// Parts are a single unit of the body, which gets repeated to match the threat.
+pub struct RangedDefender {}
+
+static RANGED_DEFENDER_PARTS: [Part; 2] = [Part::RangedAttack, Part::Move];
+
+impl CreepInfo for RangedDefender {
+    fn role() -> &'static str {
+        "ranged_defender"
+    }
+
+    fn parts() -> &'static [Part] {
+        &RANGED_DEFENDER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&RANGED_DEFENDER_PARTS)
+    }
+
+    fn boosts() -> &'static [ResourceType] {
+        &[ResourceType::CatalyzedKeaniumAlkalide]
+    }
+}
+
+
+/// Information for creating and using a healer defender.
+/// Parts are a single unit of the body, which gets repeated to match the threat.
+pub struct HealerDefender {}
+
+static HEALER_DEFENDER_PARTS: [Part; 2] = [Part::Heal, Part::Move];
+
+impl CreepInfo for HealerDefender {
+    fn role() -> &'static str {
+        "healer_defender"
+    }
+
+    fn parts() -> &'static [Part] {
+        &HEALER_DEFENDER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&HEALER_DEFENDER_PARTS)
+    }
+
+    fn boosts() -> &'static [ResourceType] {
+        &[ResourceType::CatalyzedLemergiumAlkalide]
+    }
+}
+
+
+/// Information for creating and using an attacker sent after invader cores.
+/// Parts are a single unit of the body, which gets repeated to match the core.
+pub struct CoreAttacker {}
+
+static CORE_ATTACKER_PARTS: [Part; 2] = [Part::Attack, Part::Move];
+
+impl CreepInfo for CoreAttacker {
+    fn role() -> &'static str {
+        "core_attacker"
+    }
+
+    fn parts() -> &'static [Part] {
+        &CORE_ATTACKER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&CORE_ATTACKER_PARTS)
+    }
+
+    fn boosts() -> &'static [ResourceType] {
+        &[ResourceType::CatalyzedUtriumAcid]
+    }
+
+    fn boost_policy() -> BoostPolicy {
+        BoostPolicy::Wait
+    }
+}
+
+
+/// Information for creating and using a reserver for remote rooms.
+/// Parts are a single unit of the body; two units out-pace the reservation decay.
+pub struct Reserver {}
+
+static RESERVER_PARTS: [Part; 2] = [Part::Claim, Part::Move];
+
+impl CreepInfo for Reserver {
+    fn role() -> &'static str {
+        "reserver"
+    }
+
+    fn parts() -> &'static [Part] {
+        &RESERVER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&RESERVER_PARTS)
+    }
+
+    fn max_units() -> u32 {
+        2
+    }
+}
+
+
+/// Information for creating and using a static miner in remote rooms.
+/// Parts are a single unit of the body; five units fully drain a reserved source.
+pub struct RemoteMiner {}
+
+static REMOTE_MINER_PARTS: [Part; 2] = [Part::Work, Part::Move];
+
+impl CreepInfo for RemoteMiner {
+    fn role() -> &'static str {
+        "remote_miner"
+    }
+
+    fn parts() -> &'static [Part] {
+        &REMOTE_MINER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&REMOTE_MINER_PARTS)
+    }
+
+    fn max_units() -> u32 {
+        5
+    }
+}
+
+
+/// Information for creating and using a hauler bringing energy back from remote rooms.
+/// Parts are a single unit of the body, which gets repeated to match the trip length.
+pub struct RemoteHauler {}
+
+static REMOTE_HAULER_PARTS: [Part; 2] = [Part::Carry, Part::Move];
+
+impl CreepInfo for RemoteHauler {
+    fn role() -> &'static str {
+        "remote_hauler"
+    }
+
+    fn parts() -> &'static [Part] {
+        &REMOTE_HAULER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&REMOTE_HAULER_PARTS)
+    }
+}
+
+
+/// Information for creating and using a claimer, taking the controller of a new room
+pub struct Claimer {}
+
+static CLAIMER_PARTS: [Part; 2] = [Part::Claim, Part::Move];
+
+impl CreepInfo for Claimer {
+    fn role() -> &'static str {
+        "claimer"
+    }
+
+    fn parts() -> &'static [Part] {
+        &CLAIMER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&CLAIMER_PARTS)
+    }
+}
+
+
+/// Information for creating and using a pioneer, building up a newly claimed room.
+/// Parts are a single unit of the body, which gets repeated as the parent room can afford.
+pub struct Pioneer {}
+
+static PIONEER_PARTS: [Part; 4] = [Part::Work, Part::Carry, Part::Move, Part::Move];
+
+impl CreepInfo for Pioneer {
+    fn role() -> &'static str {
+        "pioneer"
+    }
+
+    fn parts() -> &'static [Part] {
+        &PIONEER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&PIONEER_PARTS)
+    }
+}
+
+
+/// Information for creating and using a scout, keeping room intel up to date
+pub struct Scout {}
+
+static SCOUT_PARTS: [Part; 1] = [Part::Move];
+
+impl CreepInfo for Scout {
+    fn role() -> &'static str {
+        "scout"
+    }
+
+    fn parts() -> &'static [Part] {
+        &SCOUT_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&SCOUT_PARTS)
+    }
+}
+
+
+/// Information for creating and using a miner working the mineral in an owned room.
+/// Parts are a single unit of the body, which gets repeated as the room can afford.
+pub struct MineralMiner {}
+
+static MINERAL_MINER_PARTS: [Part; 4] = [Part::Work, Part::Work, Part::Carry, Part::Move];
+
+impl CreepInfo for MineralMiner {
+    fn role() -> &'static str {
+        "mineral_miner"
+    }
+
+    fn parts() -> &'static [Part] {
+        &MINERAL_MINER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&MINERAL_MINER_PARTS)
+    }
+
+    fn max_units() -> u32 {
+        6
+    }
+}
+
+
+/// Information for creating and using a carrier, moving resources between structures in a room.
+/// Parts are a single unit of the body, which gets repeated as the room can afford.
+pub struct Carrier {}
+
+static CARRIER_PARTS: [Part; 3] = [Part::Carry, Part::Carry, Part::Move];
+
+impl CreepInfo for Carrier {
+    fn role() -> &'static str {
+        "carrier"
+    }
+
+    fn parts() -> &'static [Part] {
+        &CARRIER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&CARRIER_PARTS)
+    }
+
+    fn max_units() -> u32 {
+        8
+    }
+}
+
+
+/// Information for creating and using an upgrader, spending storage energy on the controller.
+/// Parts are a single unit of the body, which gets repeated as the room can afford.
+pub struct Upgrader {}
+
+static UPGRADER_PARTS: [Part; 4] = [Part::Work, Part::Work, Part::Carry, Part::Move];
+
+impl CreepInfo for Upgrader {
+    fn role() -> &'static str {
+        "upgrader"
+    }
+
+    fn parts() -> &'static [Part] {
+        &UPGRADER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&UPGRADER_PARTS)
+    }
+
+    fn boosts() -> &'static [ResourceType] {
+        &[ResourceType::CatalyzedGhodiumAcid]
+    }
+
+    fn max_units() -> u32 {
+        5
+    }
+}
+
+/// Information for creating and using an attacker breaking a power bank.
+/// Parts are a single unit of the body, repeated as far as its healer can keep up with.
+pub struct PowerAttacker {}
+
+static POWER_ATTACKER_PARTS: [Part; 2] = [Part::Attack, Part::Move];
+
+impl CreepInfo for PowerAttacker {
+    fn role() -> &'static str {
+        "power_attacker"
+    }
+
+    fn parts() -> &'static [Part] {
+        &POWER_ATTACKER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&POWER_ATTACKER_PARTS)
+    }
+}
+
+
+/// Information for creating and using a healer keeping a power bank attacker alive.
+/// Parts are a single unit of the body, which gets repeated as the room can afford.
+pub struct PowerHealer {}
+
+static POWER_HEALER_PARTS: [Part; 2] = [Part::Heal, Part::Move];
+
+impl CreepInfo for PowerHealer {
+    fn role() -> &'static str {
+        "power_healer"
+    }
+
+    fn parts() -> &'static [Part] {
+        &POWER_HEALER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&POWER_HEALER_PARTS)
+    }
+}
+
+
+/// Information for creating and using a hauler bringing power home from a broken bank.
+/// Parts are a single unit of the body, which gets repeated as the room can afford.
+pub struct PowerHauler {}
+
+static POWER_HAULER_PARTS: [Part; 2] = [Part::Carry, Part::Move];
+
+impl CreepInfo for PowerHauler {
+    fn role() -> &'static str {
+        "power_hauler"
+    }
+
+    fn parts() -> &'static [Part] {
+        &POWER_HAULER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&POWER_HAULER_PARTS)
+    }
+}
+
+
+/// Information for creating and using a harvester working a deposit in a highway room.
+/// Parts are a single unit of the body, which gets repeated as the room can afford.
+pub struct DepositHarvester {}
+
+static DEPOSIT_HARVESTER_PARTS: [Part; 3] = [Part::Work, Part::Carry, Part::Move];
+
+impl CreepInfo for DepositHarvester {
+    fn role() -> &'static str {
+        "deposit_harvester"
+    }
+
+    fn parts() -> &'static [Part] {
+        &DEPOSIT_HARVESTER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&DEPOSIT_HARVESTER_PARTS)
+    }
+}
+
+
+/// Information for creating and using a hauler bringing deposit resources home.
+/// Parts are a single unit of the body, which gets repeated as the room can afford.
+pub struct DepositHauler {}
+
+static DEPOSIT_HAULER_PARTS: [Part; 2] = [Part::Carry, Part::Move];
+
+impl CreepInfo for DepositHauler {
+    fn role() -> &'static str {
+        "deposit_hauler"
+    }
+
+    fn parts() -> &'static [Part] {
+        &DEPOSIT_HAULER_PARTS
+    }
+
+    fn cost() -> u32 {
+        body::cost(&DEPOSIT_HAULER_PARTS)
+    }
+}
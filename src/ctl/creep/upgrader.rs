@@ -0,0 +1,96 @@
+//!
+//! Controls upgrader creeps, spending storage energy on the controller
+//!
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{Creep, ResourceType, ReturnCode, StructureSpawn};
+use screeps::memory;
+
+use crate::downgrade;
+use crate::metrics;
+use crate::signing;
+use crate::stock;
+
+use super::body;
+use super::boosting;
+use super::lifecycle;
+use super::spawning;
+use super::types::{CreepInfo, Upgrader};
+
+
+/// tries to spawn an upgrader
+pub fn spawn_upgrader(spawn: &StructureSpawn) -> Result<(), String> {
+    let room = spawn.room();
+    let parts = body::scale(Upgrader::parts(), room.energy_available(), Upgrader::max_units());
+    if parts.is_empty() {
+        return Err("not enough energy for upgrader".to_string());
+    }
+
+    let mem = memory::MemoryReference::new();
+    mem.set("role", Upgrader::role());
+    mem.set("home", room.name().to_string());
+    boosting::request(&mem, Upgrader::boosts(), Upgrader::boost_policy());
+
+    let res = spawning::spawn_with_memory(spawn, &parts, mem);
+    if res == ReturnCode::Ok {
+        metrics::inc_upgraders(1);
+        Ok(())
+    } else {
+        Err(format!("couldn't spawn upgrader: {:?}", res))
+    }
+}
+
+/// runs an upgrader, which fills up at storage while the reserve allows and upgrades the controller
+pub fn run_upgrader(creep: Creep) {
+    trace!("running upgrader {}", creep.name());
+
+    if creep.spawning() {
+        return;
+    }
+
+    if creep.memory().bool("upgrading") {
+        if creep.store_used_capacity(None) == 0 {
+            creep.memory().set("upgrading", false);
+        }
+    } else if creep.store_free_capacity(None) == 0 {
+        creep.memory().set("upgrading", true);
+    }
+
+    let room = creep.room();
+    if creep.memory().bool("upgrading") {
+        match room.controller() {
+            Some(controller) => {
+                if signing::sign(&creep, &controller) {
+                    return;
+                }
+                if creep.upgrade_controller(&controller) == ReturnCode::NotInRange {
+                    creep.move_to(&controller);
+                } else {
+                    lifecycle::at_work(&creep, "home");
+                }
+            },
+            None => {
+                warn!("upgrader {} is in a room without a controller", creep.name());
+                spawning::recycle(&creep);
+            }
+        }
+        return;
+    }
+
+    // the reserve comes first, stop drawing on it once it's down, unless the controller's about to downgrade
+    let storage = match room.storage() {
+        Some(s) if stock::spend_freely(&room) || downgrade::at_risk(&room) => s,
+        _ => {
+            if creep.store_used_capacity(None) > 0 {
+                creep.memory().set("upgrading", true);
+            }
+            return;
+        }
+    };
+
+    if creep.withdraw_all(&storage, ResourceType::Energy) == ReturnCode::NotInRange {
+        creep.move_to(&storage);
+    }
+}
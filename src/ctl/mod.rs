@@ -1,7 +1,9 @@
 mod creep;
 mod room;
+mod shard;
 pub mod util;
 
 pub use creep::*;
 pub use room::*;
+pub use shard::*;
 pub use util::metrics;
\ No newline at end of file
@@ -0,0 +1,125 @@
+//!
+//! Per-room creep census
+//!
+//! Taken once a tick after the creeps have run, grouping every creep by the home room it was
+//! spawned for, its role & what it's up to. Spawn decisions count creeps through it, so each
+//! room only weighs its own creeps, wherever they happen to be standing. Creeps still spawning
+//! count as working, and so do the items queued for a room's spawns this tick. Retiring creeps,
+//! and creeps with fewer ticks to live than their replacement takes to spawn, don't.
+//!
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use screeps::prelude::*;
+use screeps::{Creep, RoomName};
+use screeps::constants::*;
+
+
+/// What a creep is up to, as far as spawning is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreepState {
+    /// still being spawned
+    Spawning,
+    /// on the job
+    Working,
+    /// at a spawn being renewed, back on the job shortly
+    Renewing,
+    /// replaced, or on its way to be recycled
+    Retiring,
+}
+
+impl CreepState {
+    fn of(creep: &Creep) -> CreepState {
+        let mem = creep.memory();
+        if creep.spawning() {
+            CreepState::Spawning
+        } else if mem.bool("retiring") || mem.bool("recycling") {
+            CreepState::Retiring
+        } else if mem.bool("renewing") {
+            CreepState::Renewing
+        } else {
+            CreepState::Working
+        }
+    }
+}
+
+/// Creeps of one role from one home room
+#[derive(Debug, Clone, Default)]
+pub struct RoleCount {
+    pub spawning: u32,
+    pub working: u32,
+    pub renewing: u32,
+    pub retiring: u32,
+    /// working creeps that die before a replacement could be spawned
+    pub expiring: u32,
+    /// spawns queued for the role this tick
+    pub queued: u32,
+    /// ticks to live of the working creeps, lowest first
+    pub ticks_to_live: Vec<u32>,
+}
+
+impl RoleCount {
+    /// Creeps that fill the role now or shortly, counting queued spawns
+    pub fn active(&self) -> u32 {
+        (self.spawning + self.working + self.renewing + self.queued).saturating_sub(self.expiring)
+    }
+
+    fn add(&mut self, creep: &Creep) {
+        match CreepState::of(creep) {
+            CreepState::Spawning => self.spawning += 1,
+            CreepState::Renewing => self.renewing += 1,
+            CreepState::Retiring => self.retiring += 1,
+            CreepState::Working => {
+                self.working += 1;
+                let ttl = creep.ticks_to_live();
+                if ttl < creep.body().len() as u32 * CREEP_SPAWN_TIME {
+                    self.expiring += 1;
+                }
+                let at = self.ticks_to_live.partition_point(|t| *t < ttl);
+                self.ticks_to_live.insert(at, ttl);
+            },
+        }
+    }
+}
+
+/// All our creeps this tick, grouped by home room & role
+#[derive(Debug, Default)]
+pub struct Census {
+    counts: RefCell<HashMap<(RoomName, String), RoleCount>>,
+}
+
+impl Census {
+    /// Counts every creep. Creeps spawned before homes were recorded count towards the room
+    /// they're in.
+    pub fn take() -> Census {
+        let mut counts: HashMap<(RoomName, String), RoleCount> = HashMap::new();
+        for creep in screeps::game::creeps::values() {
+            let mem = creep.memory();
+            let role = match mem.string("role").ok().flatten() {
+                Some(r) => r,
+                None => continue,
+            };
+            let home = mem.string("home").ok().flatten()
+                .and_then(|h| h.parse::<RoomName>().ok())
+                .unwrap_or_else(|| creep.room().name());
+            counts.entry((home, role)).or_default().add(&creep);
+        }
+        Census { counts: RefCell::new(counts) }
+    }
+
+    /// Gets the count of a home room's creeps with a role
+    pub fn role(&self, home: RoomName, role: &str) -> RoleCount {
+        self.counts.borrow().get(&(home, role.to_string())).cloned().unwrap_or_default()
+    }
+
+    /// Creeps filling a role for a home room now or shortly
+    pub fn active(&self, home: RoomName, role: &str) -> u32 {
+        self.role(home, role).active()
+    }
+
+    /// Records a spawn queued for a home room's role, so it's counted for the rest of the tick
+    pub fn queue(&self, home: RoomName, role: &str) {
+        self.counts.borrow_mut().entry((home, role.to_string())).or_default().queued += 1;
+    }
+}
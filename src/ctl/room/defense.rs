@@ -0,0 +1,152 @@
+//!
+//! Threat assessment for a room
+//!
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Creep, Position, Room, Structure};
+use screeps::constants::*;
+use screeps::creep::Part;
+
+use crate::ctl::creep::body;
+
+
+/// How dangerous the hostiles in a room currently are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThreatLevel {
+    /// No hostiles in the room
+    None,
+    /// Hostiles without any attack or heal parts (scouts, claimers, etc.)
+    Low,
+    /// Hostiles that can fight, but that the towers can out-damage
+    Medium,
+    /// Hostiles that heal faster than the towers can damage them
+    High,
+}
+
+/// Summary of the hostile presence in a room and the room's ability to deal with it
+#[derive(Debug, Clone, Copy)]
+pub struct ThreatAssessment {
+    pub level: ThreatLevel,
+    /// number of hostile creeps
+    pub hostiles: u32,
+    /// combined melee damage per tick of all hostiles
+    pub melee_dps: u32,
+    /// combined ranged damage per tick of all hostiles
+    pub ranged_dps: u32,
+    /// combined healing per tick of all hostiles
+    pub heal: u32,
+    /// damage per tick our towers can put on the first fighting hostile, or any hostile if none fight
+    pub tower_dps: u32,
+}
+
+impl ThreatAssessment {
+    /// Looks over the hostile creeps in the room and rates the threat they pose
+    pub fn assess(room: &Room) -> ThreatAssessment {
+        let hostiles = room.find(find::HOSTILE_CREEPS);
+
+        let mut threat = ThreatAssessment {
+            level: ThreatLevel::None,
+            hostiles: hostiles.len() as u32,
+            melee_dps: 0,
+            ranged_dps: 0,
+            heal: 0,
+            tower_dps: 0,
+        };
+
+        if hostiles.is_empty() {
+            return threat;
+        }
+
+        let mut fighter: Option<&Creep> = None;
+        for hostile in hostiles.iter() {
+            let (melee, ranged, heal) = combat_power(hostile);
+            threat.melee_dps += melee;
+            threat.ranged_dps += ranged;
+            threat.heal += heal;
+
+            if fighter.is_none() && (melee + ranged + heal) > 0 {
+                fighter = Some(hostile);
+            }
+        }
+
+        // unarmed hostiles are all the same to the towers, so aim at the first of them
+        let target = fighter.unwrap_or(&hostiles[0]);
+        threat.tower_dps = tower_damage_at(room, target.pos());
+        threat.level = match fighter {
+            None => ThreatLevel::Low,
+            Some(_) if threat.tower_dps > threat.heal => ThreatLevel::Medium,
+            Some(_) => ThreatLevel::High,
+        };
+
+        debug!("room {} threat: {:?}", room.name(), threat);
+        threat
+    }
+
+    /// combined damage per tick of all hostiles
+    pub fn dps(&self) -> u32 {
+        self.melee_dps + self.ranged_dps
+    }
+
+    /// Number of (melee, ranged, healer) defenders the room should have to deal with the threat
+    pub fn defenders_wanted(&self) -> (u32, u32, u32) {
+        match self.level {
+            ThreatLevel::None => (0, 0, 0),
+            // towers can deal with scouts & claimers, but without them something has to
+            ThreatLevel::Low if self.tower_dps == 0 => (1, 0, 0),
+            ThreatLevel::Low => (0, 0, 0),
+            // kiting attackers are better answered at range
+            ThreatLevel::Medium if self.ranged_dps > self.melee_dps => (0, 1, 0),
+            ThreatLevel::Medium => (1, 0, 0),
+            ThreatLevel::High => (1, 1, 1),
+        }
+    }
+
+    /// Whether any defenders are wanted at all
+    pub fn needs_defenders(&self) -> bool {
+        self.defenders_wanted() != (0, 0, 0)
+    }
+}
+
+
+/// Determines the (melee, ranged, heal) output per tick of a creep, taking damaged parts
+/// and boosts into account
+pub fn combat_power(creep: &Creep) -> (u32, u32, u32) {
+    creep.body().iter()
+        .filter(|p| p.hits > 0)
+        .fold((0, 0, 0), |(melee, ranged, heal), p| {
+            let mult = body::boost_multiplier(p.part, p.boost);
+            match p.part {
+                Part::Attack => (melee + ATTACK_POWER * mult, ranged, heal),
+                Part::RangedAttack => (melee, ranged + RANGED_ATTACK_POWER * mult, heal),
+                Part::Heal => (melee, ranged, heal + HEAL_POWER * mult),
+                _ => (melee, ranged, heal),
+            }
+        })
+}
+
+/// Damage a single tower deals at the given range
+pub fn tower_damage(range: u32) -> u32 {
+    if range <= TOWER_OPTIMAL_RANGE {
+        TOWER_POWER_ATTACK
+    } else if range >= TOWER_FALLOFF_RANGE {
+        (TOWER_POWER_ATTACK as f32 * (1.0 - TOWER_FALLOFF)) as u32
+    } else {
+        let falloff = (range - TOWER_OPTIMAL_RANGE) as f32
+            / (TOWER_FALLOFF_RANGE - TOWER_OPTIMAL_RANGE) as f32;
+        (TOWER_POWER_ATTACK as f32 * (1.0 - TOWER_FALLOFF * falloff)) as u32
+    }
+}
+
+/// Combined damage all of our towers with enough energy to fire can deal to a position
+pub fn tower_damage_at(room: &Room, pos: Position) -> u32 {
+    room.find(find::MY_STRUCTURES).into_iter()
+        .filter_map(|s| match s.as_structure() {
+            Structure::Tower(t) => Some(t),
+            _ => None,
+        })
+        .filter(|t| t.store_of(screeps::ResourceType::Energy) >= TOWER_ENERGY_COST)
+        .fold(0, |dmg, t| dmg + tower_damage(t.pos().get_range_to(&pos)))
+}
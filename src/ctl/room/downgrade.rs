@@ -0,0 +1,49 @@
+//!
+//! Controller downgrade protection
+//!
+//! An owned controller is at risk once its ticks to downgrade drop below `downgrade.margin`,
+//! or half the level's full timer at low levels where that's shorter. While it is, builders &
+//! harvesters in the room put what they carry into the controller before building, repairing
+//! or filling the spawn, and upgraders may draw on the storage reserve. If none of them are
+//! around, the room spawns one ahead of anything else, even while under attack.
+//!
+
+use screeps::prelude::*;
+use screeps::{ResourceType, Room};
+use screeps::constants::*;
+
+use crate::ctl::creep::types::{BasicBuilder, BasicHarvester, CreepInfo, Upgrader};
+use crate::util::config;
+
+use super::census::Census;
+
+
+/// Storage energy that's enough to send an upgrader instead of a harvester
+const UPGRADER_ENERGY: u32 = 10_000;
+
+
+/// Ticks to downgrade below which a controller of the given level is at risk
+pub fn margin(level: u32) -> u32 {
+    let full = controller_downgrade(level).unwrap_or(0);
+    config::u32_or("downgrade.margin", 10_000).min(full / 2)
+}
+
+/// Whether the room's controller is ours & close enough to downgrading that upgrading comes first
+pub fn at_risk(room: &Room) -> bool {
+    match room.controller() {
+        Some(c) if c.my() => c.ticks_to_downgrade() < margin(c.level()),
+        _ => false,
+    }
+}
+
+/// Whether the room's controller is at risk with no creep of its own around that could upgrade it
+pub fn needs_creep(room: &Room, census: &Census) -> bool {
+    at_risk(room)
+        && [BasicHarvester::role(), BasicBuilder::role(), Upgrader::role()].iter()
+            .all(|role| census.active(room.name(), role) == 0)
+}
+
+/// Whether an upgrader would have the storage energy to work with, rather than a harvester
+pub fn upgrader_has_energy(room: &Room) -> bool {
+    room.storage().map(|s| s.store_of(ResourceType::Energy) >= UPGRADER_ENERGY).unwrap_or(false)
+}
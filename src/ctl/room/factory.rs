@@ -0,0 +1,218 @@
+//!
+//! Factory production
+//!
+//! Every `PLAN_TICKS` the room picks one product for its factory, most useful first:
+//! energy back out of batteries when storage runs low, then commodities (the higher tier ones
+//! once a power creep has raised the factory's level), then bars from minerals held beyond
+//! their stock target, minerals back out of bars when they run short, and batteries while
+//! storage is full of energy. Recipes come from the game's `COMMODITIES`.
+//!
+//! Carriers bring the components in & take everything else out through haul tasks.
+//! The current product is kept in `Memory.rooms.<name>.factory`.
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{PowerType, ResourceType, ReturnCode, Room, Structure, StructureFactory};
+use screeps::memory;
+
+use crate::metrics;
+use crate::util::{self, config, Commodity};
+
+use super::logistics::{HaulTask, MIN_HAUL};
+use super::stock;
+
+
+/// Fewest ticks between replanning the factory's product
+const PLAN_TICKS: u32 = 50;
+
+/// Runs' worth of components the factory is stocked with at a time
+const STOCK_RUNS: u32 = 10;
+
+
+/// What a factory product does with the room's resources
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProductKind {
+    /// batteries, compressing energy
+    Battery,
+    /// energy, out of batteries
+    Energy,
+    /// bars, compressing a mineral
+    Bar,
+    /// a mineral, out of its bar
+    Mineral,
+    /// anything else, made from bars, deposit resources & other commodities
+    Commodity,
+}
+
+impl ProductKind {
+    fn of(product: ResourceType) -> ProductKind {
+        use ResourceType::*;
+        match product {
+            Battery => ProductKind::Battery,
+            Energy => ProductKind::Energy,
+            UtriumBar | LemergiumBar | ZynthiumBar | KeaniumBar | GhodiumMelt | Oxidant | Reductant | Purifier
+                => ProductKind::Bar,
+            Utrium | Lemergium | Zynthium | Keanium | Ghodium | Oxygen | Hydrogen | Catalyst => ProductKind::Mineral,
+            _ => ProductKind::Commodity,
+        }
+    }
+}
+
+
+/// Gets the room's factory, if it has one
+pub fn factory(room: &Room) -> Option<StructureFactory> {
+    room.find(find::MY_STRUCTURES).into_iter()
+        .find_map(|s| match s.as_structure() {
+            Structure::Factory(f) => Some(f),
+            _ => None,
+        })
+}
+
+/// Amount of a resource the room has for the factory, in storage, terminal & the factory itself
+fn available(room: &Room, factory: &StructureFactory, resource: ResourceType) -> u32 {
+    stock::stored(room, resource) + factory.store_of(resource)
+}
+
+/// Level the factory can produce at right now: its level while a power creep's operate effect lasts
+fn working_level(factory: &StructureFactory) -> u32 {
+    if util::has_effect(&factory.id().to_string(), PowerType::OperateFactory) {
+        util::factory_level(factory)
+    } else {
+        0
+    }
+}
+
+/// Whether a product is worth making right now, given the room's stock
+fn wanted(room: &Room, recipe: &Commodity) -> bool {
+    let energy = stock::stored(room, ResourceType::Energy);
+    match ProductKind::of(recipe.product) {
+        ProductKind::Energy => energy < config::u32_or("factory.battery_below", stock::energy_reserve()),
+        ProductKind::Battery => energy > config::u32_or("factory.battery_above", 400_000),
+        ProductKind::Bar => recipe.components.iter()
+            .filter(|(r, _)| *r != ResourceType::Energy)
+            .all(|(r, amount)| stock::stored(room, *r) >= stock::room_target(*r) + amount),
+        ProductKind::Mineral => stock::stored(room, recipe.product) < stock::terminal_target(recipe.product),
+        ProductKind::Commodity => energy >= stock::energy_reserve()
+            && config::bool_or("factory.commodities", true),
+    }
+}
+
+/// Order products are considered in: energy first while storage is low, batteries last
+fn rank(recipe: &Commodity) -> (u8, std::cmp::Reverse<u32>) {
+    let kind = match ProductKind::of(recipe.product) {
+        ProductKind::Energy => 0,
+        ProductKind::Commodity => 1,
+        ProductKind::Bar => 2,
+        ProductKind::Mineral => 3,
+        ProductKind::Battery => 4,
+    };
+    // higher tier commodities first
+    (kind, std::cmp::Reverse(recipe.level.unwrap_or(0)))
+}
+
+/// Picks what the factory should make from what the room holds
+fn choose(room: &Room, factory: &StructureFactory) -> Option<Commodity> {
+    let level = working_level(factory);
+    let mut recipes: Vec<Commodity> = util::commodity_products().into_iter()
+        .filter_map(util::commodity)
+        .filter(|c| c.level.map(|l| l == level).unwrap_or(true))
+        .filter(|c| c.components.iter().all(|(r, amount)| available(room, factory, *r) >= *amount))
+        .filter(|c| wanted(room, c))
+        .collect();
+    recipes.sort_by_key(rank);
+    recipes.into_iter().next()
+}
+
+/// Gets the factory's current product, replanning it every `PLAN_TICKS`
+pub fn product(room: &Room) -> Option<Commodity> {
+    let factory = factory(room)?;
+    let path = format!("rooms.{}.factory", room.name());
+    let mem = memory::root();
+    let time = screeps::game::time();
+
+    let planned = mem.path_i32(&format!("{}.planned", path)).ok().flatten().map(|t| t as u32);
+    if matches!(planned, Some(t) if time.saturating_sub(t) < PLAN_TICKS) {
+        let product = mem.path_string(&format!("{}.product", path)).ok().flatten()
+            .and_then(|r| ResourceType::from_str(&r).ok())?;
+        return util::commodity(product);
+    }
+
+    let recipe = if config::bool_or("factory.enabled", true) {
+        choose(room, &factory)
+    } else {
+        None
+    };
+
+    mem.path_set(&format!("{}.planned", path), time);
+    match &recipe {
+        Some(r) => {
+            debug!("room {} factory producing {}", room.name(), util::resource_name(r.product));
+            mem.path_set(&format!("{}.product", path), util::resource_name(r.product));
+        },
+        None => mem.path_del(&format!("{}.product", path)),
+    }
+    recipe
+}
+
+/// Produces the current product when the factory is off cooldown & holds a run's worth of components
+pub fn run(room: &Room) {
+    let factory = match factory(room) {
+        Some(f) => f,
+        None => return,
+    };
+    let recipe = match product(room) {
+        Some(r) => r,
+        None => return,
+    };
+    if factory.cooldown() > 0 || !recipe.components.iter().all(|(r, amount)| factory.store_of(*r) >= *amount) {
+        return;
+    }
+
+    match factory.produce(recipe.product) {
+        ReturnCode::Ok => metrics::inc_factory_produced(&util::resource_name(recipe.product), recipe.amount),
+        r => warn!("room {} factory couldn't produce {}: {:?}", room.name(), util::resource_name(recipe.product), r),
+    }
+}
+
+/// Tasks stocking the factory with its product's components, and emptying out everything else
+pub fn haul_tasks(room: &Room) -> Vec<HaulTask> {
+    let factory = match factory(room) {
+        Some(f) => f,
+        None => return Vec::new(),
+    };
+    let (storage, terminal) = (room.storage(), room.terminal());
+    let store_id = match (&storage, &terminal) {
+        (Some(s), _) => s.id().to_string(),
+        (None, Some(t)) => t.id().to_string(),
+        (None, None) => return Vec::new(),
+    };
+    let recipe = product(room);
+    let components: Vec<(ResourceType, u32)> = recipe.as_ref().map(|r| r.components.clone()).unwrap_or_default();
+
+    let mut tasks = Vec::new();
+    for resource in factory.store_types() {
+        let held = factory.store_of(resource);
+        if !components.iter().any(|(r, _)| *r == resource) && held >= MIN_HAUL {
+            tasks.push(HaulTask { resource, from: factory.id().to_string(), to: store_id.clone(), amount: held });
+        }
+    }
+
+    for (resource, amount) in components {
+        let need = (amount * STOCK_RUNS).saturating_sub(factory.store_of(resource));
+        let from_storage = storage.as_ref().map(|s| (s.id().to_string(), s.store_of(resource)));
+        let from_terminal = terminal.as_ref().map(|t| (t.id().to_string(), t.store_of(resource)));
+        if let Some((from, held)) = from_storage.into_iter().chain(from_terminal).max_by_key(|(_, held)| *held) {
+            // small recipes still need their components, even below a usual trip
+            let amount = need.min(held);
+            if amount >= MIN_HAUL.min(need) && amount > 0 {
+                tasks.push(HaulTask { resource, from, to: factory.id().to_string(), amount });
+            }
+        }
+    }
+    tasks
+}
@@ -0,0 +1,139 @@
+//!
+//! Invader core detection & response
+//!
+//! Cores are tracked in `Memory.rooms.<name>.invader_core` for as long as they stand,
+//! so rooms can be treated as unsafe even while we don't have vision of them
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Attackable, Room, RoomName, Structure};
+use screeps::constants::*;
+use screeps::memory;
+
+use crate::util::{self, config};
+
+
+/// A known invader core
+#[derive(Debug, Clone, Copy)]
+pub struct CoreSighting {
+    pub room: RoomName,
+    pub level: u32,
+    /// hits of the core plus any rampart covering it
+    pub hits: u32,
+    /// whether the room is one of our own
+    pub owned: bool,
+}
+
+impl CoreSighting {
+    /// Whether we should try to destroy the core, rather than wait for it to decay
+    pub fn should_attack(&self) -> bool {
+        self.level <= config::u32_or("invader_core.max_attack_level", 1)
+    }
+
+    /// Number of attack units needed to take the core down in a reasonable time
+    pub fn attack_units(&self) -> u32 {
+        // give ourselves less time in our own rooms
+        let window = if self.owned {
+            config::u32_or("invader_core.owned_attack_ticks", 300)
+        } else {
+            config::u32_or("invader_core.remote_attack_ticks", 800)
+        };
+        self.hits / (ATTACK_POWER * window.max(1)) + 1
+    }
+}
+
+
+/// Looks for an invader core in the room, recording or clearing it in memory.
+/// Only rooms we own, reserve, or that neighbour an owned room are tracked.
+pub fn scan(room: &Room, username: &str) {
+    let owned = match room.controller() {
+        Some(c) if c.my() => true,
+        Some(c) => {
+            let reserved = matches!(util::reservation(&c), Some((u, _)) if u == username);
+            if !reserved && !near_owned_room(room.name()) {
+                return;
+            }
+            false
+        },
+        None => return,
+    };
+
+    let structures = room.find(find::HOSTILE_STRUCTURES);
+    let core = structures.iter()
+        .filter_map(|s| match s.clone().as_structure() {
+            Structure::InvaderCore(c) => Some(c),
+            _ => None,
+        })
+        .next();
+
+    let path = format!("rooms.{}.invader_core", room.name());
+    match core {
+        Some(core) => {
+            let rampart_hits = structures.iter()
+                .filter(|s| s.structure_type() == StructureType::Rampart && s.pos().is_equal_to(&core))
+                .fold(0, |hits, s| hits + s.hits());
+
+            if memory::root().path_dict(&path).ok().flatten().is_none() {
+                warn!("level {} invader core found in room {}", core.level(), room.name());
+            }
+            memory::root().path_set(&format!("{}.level", path), core.level());
+            memory::root().path_set(&format!("{}.hits", path), core.hits() + rampart_hits);
+            memory::root().path_set(&format!("{}.owned", path), owned);
+        },
+        None => {
+            if memory::root().path_dict(&path).ok().flatten().is_some() {
+                info!("invader core in room {} is gone", room.name());
+                memory::root().path_del(&path);
+            }
+        }
+    }
+}
+
+/// Whether an invader core is known to be standing in the room.
+/// Remote operations in the room should stay paused while this is true.
+pub fn core_present(room_name: RoomName) -> bool {
+    memory::root()
+        .path_dict(&format!("rooms.{}.invader_core", room_name))
+        .ok()
+        .flatten()
+        .is_some()
+}
+
+/// Gets all invader cores currently known
+pub fn sightings() -> Vec<CoreSighting> {
+    let rooms = match memory::root().dict("rooms") {
+        Ok(Some(r)) => r,
+        _ => return Vec::new(),
+    };
+
+    rooms.keys().into_iter()
+        .filter_map(|name| {
+            let room = RoomName::from_str(&name).ok()?;
+            let core = rooms.path_dict(&format!("{}.invader_core", name)).ok()??;
+            Some(CoreSighting {
+                room,
+                level: core.i32("level").ok()?? as u32,
+                hits: core.i32("hits").ok()?? as u32,
+                owned: core.bool("owned"),
+            })
+        })
+        .collect()
+}
+
+/// Picks the owned room that should respond to a core: the room itself if owned,
+/// otherwise the closest owned room
+pub fn responder_for(room_name: RoomName) -> Option<RoomName> {
+    util::owned_rooms().into_iter()
+        .map(|r| r.name())
+        .min_by_key(|r| screeps::game::map::get_room_linear_distance(*r, room_name, false))
+}
+
+fn near_owned_room(room_name: RoomName) -> bool {
+    util::owned_rooms().iter()
+        .any(|r| screeps::game::map::get_room_linear_distance(r.name(), room_name, false) <= 1)
+}
@@ -0,0 +1,460 @@
+//!
+//! Lab reactions & boost production
+//!
+//! Two labs within reach of the rest hold a reaction's inputs, and every lab in range of
+//! both reacts them. The room works down a list of compounds to keep in stock: the boosts
+//! defenders and upgraders use first, then `labs.target`. For whichever is short, the chain
+//! towards it is walked to find a step that can react with what's on hand, and the base
+//! minerals the chain is missing are asked for from the other terminals or the market.
+//!
+//! Labs other than the inputs can be reserved to hand out a boost for a while, and stay out of
+//! reactions until the reservation runs out. Carriers do the loading and emptying through haul
+//! tasks. The chosen labs, reservations and the current reaction are kept in
+//! `Memory.rooms.<name>.labs`.
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{HasStore, Part, ResourceType, ReturnCode, Room, Structure, StructureLab};
+use screeps::constants::*;
+use screeps::memory;
+
+use crate::util::{self, config};
+
+use super::logistics::{HaulTask, MIN_HAUL};
+use super::stock;
+
+
+/// Smallest amount worth setting up a reaction for
+const MIN_REACTION: u32 = 100;
+
+/// Amount an output lab holds before it's emptied
+const OUTPUT_EMPTY_AT: u32 = 1_000;
+
+/// Fewest ticks between replanning the room's reaction
+const PLAN_TICKS: u32 = 50;
+
+
+/// One step of a reaction chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reaction {
+    pub output: ResourceType,
+    pub inputs: (ResourceType, ResourceType),
+    /// amount of the output to make
+    pub amount: u32,
+}
+
+/// The room's labs, split into the two holding inputs, the ones reacting them,
+/// and the ones reserved for boosting with the boost each hands out
+pub struct LabSetup {
+    pub inputs: (StructureLab, StructureLab),
+    pub outputs: Vec<StructureLab>,
+    pub boosting: Vec<(StructureLab, ResourceType)>,
+}
+
+
+/// The two resources a compound is made from
+pub fn components(compound: ResourceType) -> Option<(ResourceType, ResourceType)> {
+    use ResourceType::*;
+    let inputs = match compound {
+        Hydroxide => (Hydrogen, Oxygen),
+        ZynthiumKeanite => (Zynthium, Keanium),
+        UtriumLemergite => (Utrium, Lemergium),
+        Ghodium => (ZynthiumKeanite, UtriumLemergite),
+
+        UtriumHydride => (Utrium, Hydrogen),
+        UtriumOxide => (Utrium, Oxygen),
+        KeaniumHydride => (Keanium, Hydrogen),
+        KeaniumOxide => (Keanium, Oxygen),
+        LemergiumHydride => (Lemergium, Hydrogen),
+        LemergiumOxide => (Lemergium, Oxygen),
+        ZynthiumHydride => (Zynthium, Hydrogen),
+        ZynthiumOxide => (Zynthium, Oxygen),
+        GhodiumHydride => (Ghodium, Hydrogen),
+        GhodiumOxide => (Ghodium, Oxygen),
+
+        UtriumAcid => (UtriumHydride, Hydroxide),
+        UtriumAlkalide => (UtriumOxide, Hydroxide),
+        KeaniumAcid => (KeaniumHydride, Hydroxide),
+        KeaniumAlkalide => (KeaniumOxide, Hydroxide),
+        LemergiumAcid => (LemergiumHydride, Hydroxide),
+        LemergiumAlkalide => (LemergiumOxide, Hydroxide),
+        ZynthiumAcid => (ZynthiumHydride, Hydroxide),
+        ZynthiumAlkalide => (ZynthiumOxide, Hydroxide),
+        GhodiumAcid => (GhodiumHydride, Hydroxide),
+        GhodiumAlkalide => (GhodiumOxide, Hydroxide),
+
+        CatalyzedUtriumAcid => (UtriumAcid, Catalyst),
+        CatalyzedUtriumAlkalide => (UtriumAlkalide, Catalyst),
+        CatalyzedKeaniumAcid => (KeaniumAcid, Catalyst),
+        CatalyzedKeaniumAlkalide => (KeaniumAlkalide, Catalyst),
+        CatalyzedLemergiumAcid => (LemergiumAcid, Catalyst),
+        CatalyzedLemergiumAlkalide => (LemergiumAlkalide, Catalyst),
+        CatalyzedZynthiumAcid => (ZynthiumAcid, Catalyst),
+        CatalyzedZynthiumAlkalide => (ZynthiumAlkalide, Catalyst),
+        CatalyzedGhodiumAcid => (GhodiumAcid, Catalyst),
+        CatalyzedGhodiumAlkalide => (GhodiumAlkalide, Catalyst),
+        _ => return None,
+    };
+    Some(inputs)
+}
+
+/// The boost we make for a body part, for the work our creeps use it for
+pub fn boost_for(part: Part) -> Option<ResourceType> {
+    match part {
+        Part::Attack => Some(ResourceType::CatalyzedUtriumAcid),
+        Part::RangedAttack => Some(ResourceType::CatalyzedKeaniumAlkalide),
+        Part::Heal => Some(ResourceType::CatalyzedLemergiumAlkalide),
+        Part::Work => Some(ResourceType::CatalyzedGhodiumAcid),
+        _ => None,
+    }
+}
+
+/// The body part a boost is made for
+pub fn part_for(boost: ResourceType) -> Option<Part> {
+    [Part::Attack, Part::RangedAttack, Part::Heal, Part::Work].iter()
+        .copied()
+        .find(|p| boost_for(*p) == Some(boost))
+}
+
+/// Boosts kept in stock, with the amount of each: defenders' first, then upgraders'
+pub fn boost_stocks() -> Vec<(ResourceType, u32)> {
+    let amount = config::u32_or("labs.boost_stock", 3_000);
+    [Part::Attack, Part::RangedAttack, Part::Heal, Part::Work].iter()
+        .filter_map(|p| boost_for(*p))
+        .map(|b| (b, amount))
+        .collect()
+}
+
+/// Finds the next reaction towards making an amount of a compound, given how much of each
+/// resource is available. Inputs that are short get made first, if they can be.
+pub fn next_reaction(target: ResourceType, amount: u32, available: &dyn Fn(ResourceType) -> u32) -> Option<Reaction> {
+    let (a, b) = components(target)?;
+    let can_make = amount.min(available(a)).min(available(b));
+    if can_make >= MIN_REACTION {
+        return Some(Reaction { output: target, inputs: (a, b), amount: can_make });
+    }
+
+    [a, b].iter()
+        .filter(|&&input| available(input) < amount)
+        .find_map(|&input| next_reaction(input, amount - available(input), available))
+}
+
+/// Base minerals missing to make an amount of a compound, given how much of each resource is available
+pub fn missing_minerals(target: ResourceType, amount: u32, available: &dyn Fn(ResourceType) -> u32)
+        -> Vec<(ResourceType, u32)> {
+    let (a, b) = match components(target) {
+        Some(c) => c,
+        None => return vec![(target, amount)],
+    };
+
+    let mut missing: Vec<(ResourceType, u32)> = Vec::new();
+    for &input in [a, b].iter() {
+        let short = amount.saturating_sub(available(input));
+        if short == 0 {
+            continue;
+        }
+        for (resource, amount) in missing_minerals(input, short, available) {
+            match missing.iter_mut().find(|(r, _)| *r == resource) {
+                Some((_, a)) => *a += amount,
+                None => missing.push((resource, amount)),
+            }
+        }
+    }
+    missing
+}
+
+
+/// Gets the room's lab setup, choosing the input labs again whenever labs are built or lost
+pub fn setup(room: &Room) -> Option<LabSetup> {
+    let labs: Vec<StructureLab> = room.find(find::MY_STRUCTURES).into_iter()
+        .filter_map(|s| match s.as_structure() {
+            Structure::Lab(l) if l.is_active() => Some(l),
+            _ => None,
+        })
+        .collect();
+    if labs.len() < 3 {
+        return None;
+    }
+
+    let path = format!("rooms.{}.labs", room.name());
+    let count = memory::root().path_i32(&format!("{}.count", path)).ok().flatten().unwrap_or(0) as usize;
+    let saved: Vec<String> = memory::root().path_arr(&format!("{}.inputs", path)).ok().flatten().unwrap_or_default();
+    let find_lab = |id: &String| labs.iter().position(|l| &l.id().to_string() == id);
+
+    let (a, b) = match (count == labs.len(), saved.first().and_then(find_lab), saved.get(1).and_then(find_lab)) {
+        (true, Some(a), Some(b)) => (a, b),
+        _ => {
+            let (a, b) = choose_inputs(&labs)?;
+            info!("room {} using labs {} & {} for inputs", room.name(), labs[a].id(), labs[b].id());
+            memory::root().path_set(&format!("{}.count", path), labs.len() as u32);
+            memory::root().path_set(&format!("{}.inputs", path), vec![labs[a].id().to_string(), labs[b].id().to_string()]);
+            (a, b)
+        }
+    };
+
+    let reserved = reservations(room);
+    let boosting = labs.iter()
+        .filter_map(|l| {
+            let (_, boost, _) = reserved.iter().find(|(id, _, _)| id == &l.id().to_string())?;
+            Some((l.clone(), *boost))
+        })
+        .collect();
+    let outputs = labs.iter().enumerate()
+        .filter(|&(i, l)| i != a && i != b && l.pos().in_range_to(&labs[a], 2) && l.pos().in_range_to(&labs[b], 2))
+        .filter(|(_, l)| !reserved.iter().any(|(id, _, _)| id == &l.id().to_string()))
+        .map(|(_, l)| l.clone())
+        .collect();
+    Some(LabSetup { inputs: (labs[a].clone(), labs[b].clone()), outputs, boosting })
+}
+
+/// Gets the room's lab reservations as (lab id, boost, until), dropping any that ran out
+fn reservations(room: &Room) -> Vec<(String, ResourceType, u32)> {
+    let path = format!("rooms.{}.labs.reserved", room.name());
+    let mem = match memory::root().path_dict(&path).ok().flatten() {
+        Some(m) => m,
+        None => return Vec::new(),
+    };
+    let time = screeps::game::time();
+
+    let mut reserved = Vec::new();
+    for id in mem.keys() {
+        let boost = mem.path_string(&format!("{}.boost", id)).ok().flatten()
+            .and_then(|b| ResourceType::from_str(&b).ok());
+        let until = mem.path_i32(&format!("{}.until", id)).ok().flatten().unwrap_or(0) as u32;
+        match boost {
+            Some(boost) if until >= time => reserved.push((id, boost, until)),
+            _ => mem.del(&id),
+        }
+    }
+    reserved
+}
+
+/// Reserves a lab to hand out a boost until a tick, or extends the lab's reservation if one
+/// already has it. Labs holding the boost are picked first, and one lab is always left to react.
+pub fn reserve(room: &Room, boost: ResourceType, until: u32) -> Option<StructureLab> {
+    let setup = setup(room)?;
+    let path = format!("rooms.{}.labs.reserved", room.name());
+
+    let lab = match setup.boosting.iter().find(|(_, b)| *b == boost) {
+        Some((lab, _)) => lab.clone(),
+        None => {
+            if setup.outputs.len() < 2 {
+                return None;
+            }
+            let lab = setup.outputs.iter()
+                .min_by_key(|l| match lab_mineral(l) {
+                    Some(m) if m == boost => 0,
+                    None => 1,
+                    Some(_) => 2,
+                })?
+                .clone();
+            info!("room {} reserving lab {} for {}", room.name(), lab.id(), util::resource_name(boost));
+            memory::root().path_set(&format!("{}.{}.boost", path, lab.id()), util::resource_name(boost));
+            lab
+        }
+    };
+
+    let key = format!("{}.{}.until", path, lab.id());
+    let current = memory::root().path_i32(&key).ok().flatten().unwrap_or(0) as u32;
+    memory::root().path_set(&key, until.max(current));
+    Some(lab)
+}
+
+/// Picks the pair of labs the most other labs can react from
+fn choose_inputs(labs: &[StructureLab]) -> Option<(usize, usize)> {
+    let mut best = None;
+    let mut best_reach = 0;
+    for a in 0..labs.len() {
+        for b in (a + 1)..labs.len() {
+            let reach = labs.iter().enumerate()
+                .filter(|&(i, l)| i != a && i != b && l.pos().in_range_to(&labs[a], 2) && l.pos().in_range_to(&labs[b], 2))
+                .count();
+            if reach > best_reach {
+                best = Some((a, b));
+                best_reach = reach;
+            }
+        }
+    }
+    best
+}
+
+/// The mineral or compound a lab holds, if any
+fn lab_mineral(lab: &StructureLab) -> Option<ResourceType> {
+    lab.store_types().into_iter().find(|r| *r != ResourceType::Energy)
+}
+
+/// Amount of a resource the room has for reactions, counting what's already in its labs
+fn available(room: &Room, resource: ResourceType) -> u32 {
+    let in_labs: u32 = room.find(find::MY_STRUCTURES).into_iter()
+        .filter_map(|s| match s.as_structure() {
+            Structure::Lab(l) => Some(l.store_of(resource)),
+            _ => None,
+        })
+        .sum();
+    stock::stored(room, resource) + in_labs
+}
+
+/// Compounds the room is short of, most important first, with how much is missing
+fn shortfalls(room: &Room) -> Vec<(ResourceType, u32)> {
+    let mut targets = boost_stocks();
+    if let Ok(target) = ResourceType::from_str(&config::string_or("labs.target", "")) {
+        if components(target).is_some() && !targets.iter().any(|(r, _)| *r == target) {
+            targets.push((target, stock::room_target(target)));
+        }
+    }
+
+    targets.into_iter()
+        .map(|(r, amount)| (r, amount.saturating_sub(stock::stored(room, r))))
+        .filter(|&(_, short)| short >= MIN_REACTION)
+        .collect()
+}
+
+/// Base minerals the room's reaction chains are missing
+pub fn missing(room: &Room) -> Vec<(ResourceType, u32)> {
+    if setup(room).is_none() {
+        return Vec::new();
+    }
+    let available = |r| available(room, r);
+
+    let mut missing: Vec<(ResourceType, u32)> = Vec::new();
+    for (target, short) in shortfalls(room) {
+        for (resource, amount) in missing_minerals(target, short, &available) {
+            match missing.iter_mut().find(|(r, _)| *r == resource) {
+                Some((_, a)) => *a = (*a).max(amount),
+                None => missing.push((resource, amount)),
+            }
+        }
+    }
+    missing
+}
+
+/// Gets the room's current reaction, replanning it every so often
+pub fn reaction(room: &Room) -> Option<Reaction> {
+    let path = format!("rooms.{}.labs", room.name());
+    let mem = memory::root();
+    let time = screeps::game::time();
+
+    let planned = mem.path_i32(&format!("{}.planned", path)).ok().flatten().map(|t| t as u32);
+    if matches!(planned, Some(t) if time.saturating_sub(t) < PLAN_TICKS) {
+        let output = mem.path_string(&format!("{}.reaction", path)).ok().flatten()
+            .and_then(|r| ResourceType::from_str(&r).ok())?;
+        let amount = mem.path_i32(&format!("{}.amount", path)).ok().flatten().unwrap_or(0) as u32;
+        let inputs = components(output)?;
+        return Some(Reaction { output, inputs, amount });
+    }
+
+    let available = |r| available(room, r);
+    let reaction = shortfalls(room).into_iter()
+        .find_map(|(target, short)| next_reaction(target, short, &available));
+
+    mem.path_set(&format!("{}.planned", path), time);
+    match reaction {
+        Some(r) => {
+            debug!("room {} reacting {} {}", room.name(), r.amount, util::resource_name(r.output));
+            mem.path_set(&format!("{}.reaction", path), util::resource_name(r.output));
+            mem.path_set(&format!("{}.amount", path), r.amount);
+        },
+        None => {
+            mem.path_del(&format!("{}.reaction", path));
+            mem.path_del(&format!("{}.amount", path));
+        },
+    }
+    reaction
+}
+
+/// Runs the room's current reaction in every output lab that's ready
+pub fn run(room: &Room) {
+    let setup = match setup(room) {
+        Some(s) => s,
+        None => return,
+    };
+    let reaction = match reaction(room) {
+        Some(r) => r,
+        None => return,
+    };
+
+    let (a, b) = &setup.inputs;
+    if a.store_of(reaction.inputs.0) < LAB_REACTION_AMOUNT || b.store_of(reaction.inputs.1) < LAB_REACTION_AMOUNT {
+        return;
+    }
+    for lab in setup.outputs.iter() {
+        if lab.cooldown() > 0 || matches!(lab_mineral(lab), Some(m) if m != reaction.output) {
+            continue;
+        }
+        let r = lab.run_reaction(a, b);
+        if r != ReturnCode::Ok {
+            warn!("lab {} in room {} couldn't react: {:?}", lab.id(), room.name(), r);
+        }
+    }
+}
+
+/// Tasks keeping the labs going: loading inputs, emptying outputs, and clearing out anything
+/// left from an earlier reaction
+pub fn haul_tasks(room: &Room) -> Vec<HaulTask> {
+    let setup = match setup(room) {
+        Some(s) => s,
+        None => return Vec::new(),
+    };
+    let (storage, terminal) = (room.storage(), room.terminal());
+    let store_id = match (&storage, &terminal) {
+        (Some(s), _) => s.id().to_string(),
+        (None, Some(t)) => t.id().to_string(),
+        (None, None) => return Vec::new(),
+    };
+    let reaction = reaction(room);
+
+    let mut tasks = Vec::new();
+    let empty = |lab: &StructureLab, resource: ResourceType| HaulTask {
+        resource,
+        from: lab.id().to_string(),
+        to: store_id.clone(),
+        amount: lab.store_of(resource),
+    };
+    // fills a lab up to an amount of a resource, from whichever of storage & terminal holds more
+    let fill = |lab: &StructureLab, resource: ResourceType, amount: u32| {
+        let need = amount.saturating_sub(lab.store_of(resource));
+        let from_storage = storage.as_ref().map(|s| (s.id().to_string(), s.store_of(resource)));
+        let from_terminal = terminal.as_ref().map(|t| (t.id().to_string(), t.store_of(resource)));
+        let (from, held) = from_storage.into_iter().chain(from_terminal).max_by_key(|(_, held)| *held)?;
+        let amount = need.min(held);
+        if amount < MIN_HAUL {
+            return None;
+        }
+        Some(HaulTask { resource, from, to: lab.id().to_string(), amount })
+    };
+
+    // boosting labs come first, there's a creep waiting on them
+    for (lab, boost) in setup.boosting.iter() {
+        match lab_mineral(lab) {
+            Some(held) if held != *boost => tasks.push(empty(lab, held)),
+            _ => tasks.extend(fill(lab, *boost, LAB_MINERAL_CAPACITY)),
+        }
+        tasks.extend(fill(lab, ResourceType::Energy, LAB_ENERGY_CAPACITY));
+    }
+
+    let (a, b) = &setup.inputs;
+    let inputs = [(a, reaction.map(|r| r.inputs.0)), (b, reaction.map(|r| r.inputs.1))];
+    let reaction_amount = reaction.map(|r| r.amount).unwrap_or(0).min(LAB_MINERAL_CAPACITY);
+    for (lab, wanted) in inputs.iter() {
+        match (lab_mineral(lab), wanted) {
+            (Some(held), _) if Some(held) != *wanted => tasks.push(empty(lab, held)),
+            (_, Some(wanted)) => tasks.extend(fill(lab, *wanted, reaction_amount)),
+            _ => (),
+        }
+    }
+
+    for lab in setup.outputs.iter() {
+        if let Some(held) = lab_mineral(lab) {
+            if Some(held) != reaction.map(|r| r.output) || lab.store_of(held) >= OUTPUT_EMPTY_AT {
+                tasks.push(empty(lab, held));
+            }
+        }
+    }
+
+    tasks
+}
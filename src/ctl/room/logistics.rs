@@ -0,0 +1,123 @@
+//!
+//! Moving resources between structures inside a room
+//!
+//! Anything that needs stocking up or emptying out hands carriers a `HaulTask`:
+//! the labs' inputs & outputs, the factory, the power spawn, and the terminal, kept at its
+//! stock targets from storage.
+//!
+
+use std::str::FromStr;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{ResourceType, Room};
+use screeps::memory::MemoryReference;
+
+use crate::ctl::creep::types::{Carrier, CreepInfo};
+use crate::util;
+
+use super::factory;
+use super::labs;
+use super::powerspawn;
+use super::stock;
+
+
+/// Smallest amount worth a trip
+pub const MIN_HAUL: u32 = 100;
+
+
+/// A load for a carrier to take from one structure to another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HaulTask {
+    pub resource: ResourceType,
+    /// id of the structure to take from
+    pub from: String,
+    /// id of the structure to deliver to
+    pub to: String,
+    pub amount: u32,
+}
+
+impl HaulTask {
+    /// Saves the task into a creep's memory
+    pub fn save(&self, mem: &MemoryReference) {
+        mem.set("resource", util::resource_name(self.resource));
+        mem.set("from", self.from.as_str());
+        mem.set("to", self.to.as_str());
+        mem.set("amount", self.amount);
+    }
+
+    /// Loads the task from a creep's memory, if it has one
+    pub fn load(mem: &MemoryReference) -> Option<HaulTask> {
+        Some(HaulTask {
+            resource: ResourceType::from_str(&mem.string("resource").ok()??).ok()?,
+            from: mem.string("from").ok()??,
+            to: mem.string("to").ok()??,
+            amount: mem.i32("amount").ok()?? as u32,
+        })
+    }
+
+    /// Removes the task from a creep's memory
+    pub fn clear(mem: &MemoryReference) {
+        for key in ["resource", "from", "to", "amount"].iter() {
+            mem.del(key);
+        }
+    }
+}
+
+
+/// Gets everything in the room waiting to be hauled, most important first
+pub fn tasks(room: &Room) -> Vec<HaulTask> {
+    let mut tasks = labs::haul_tasks(room);
+    tasks.extend(factory::haul_tasks(room));
+    tasks.extend(powerspawn::haul_tasks(room));
+    tasks.extend(terminal_tasks(room));
+    tasks
+}
+
+/// Picks the most important task no other carrier in the room has taken
+pub fn next_task(room: &Room) -> Option<HaulTask> {
+    let taken: Vec<HaulTask> = room.find(find::MY_CREEPS).iter()
+        .filter(|c| c.memory().string("role").ok().flatten().as_deref() == Some(Carrier::role()))
+        .filter_map(|c| HaulTask::load(&c.memory()))
+        .collect();
+
+    tasks(room).into_iter()
+        .find(|t| !taken.iter().any(|o| o.resource == t.resource && o.from == t.from && o.to == t.to))
+}
+
+/// Number of carriers the room needs for its hauling
+pub fn carriers_wanted(room: &Room) -> u32 {
+    if tasks(room).is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+/// Tasks keeping the terminal at its targets: surplus goes to storage, shortfalls come from it
+fn terminal_tasks(room: &Room) -> Vec<HaulTask> {
+    let (storage, terminal) = match (room.storage(), room.terminal()) {
+        (Some(s), Some(t)) if t.my() => (s, t),
+        _ => return Vec::new(),
+    };
+    let storage_id = storage.id().to_string();
+    let terminal_id = terminal.id().to_string();
+
+    let mut tasks = Vec::new();
+    for resource in terminal.store_types() {
+        let excess = terminal.store_of(resource).saturating_sub(stock::terminal_target(resource));
+        if excess >= MIN_HAUL && storage.store_free_capacity(Some(resource)) > 0 {
+            tasks.push(HaulTask { resource, from: terminal_id.clone(), to: storage_id.clone(), amount: excess });
+        }
+    }
+    for resource in storage.store_types() {
+        let missing = stock::terminal_target(resource).saturating_sub(terminal.store_of(resource));
+        let amount = missing.min(storage.store_of(resource));
+        if amount >= MIN_HAUL && terminal.store_free_capacity(Some(resource)) > 0 {
+            tasks.push(HaulTask { resource, from: storage_id.clone(), to: terminal_id.clone(), amount });
+        }
+    }
+
+    tasks.sort_by_key(|t| std::cmp::Reverse(t.amount));
+    tasks
+}
@@ -0,0 +1,110 @@
+//!
+//! Mineral harvesting in owned rooms
+//!
+//! An extractor goes up at RCL 6, along with a container next to the mineral for the miner
+//! to fill. Mining only runs while the mineral has some left and we're below the stock cap.
+//!
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find, look};
+use screeps::{HasStore, Mineral, Position, Room, StructureType, Terrain};
+
+use crate::ctl::creep::types::{CreepInfo, MineralMiner};
+use crate::util::{self, config};
+
+
+/// Controller level extractors unlock at
+const EXTRACTOR_RCL: u32 = 6;
+
+
+/// Places the extractor & container construction sites for the room's mineral, if missing
+pub fn plan(room: &Room) {
+    if room.controller().map(|c| c.level()).unwrap_or(0) < EXTRACTOR_RCL {
+        return;
+    }
+    let mineral = match room.find(find::MINERALS).into_iter().next() {
+        Some(m) => m,
+        None => return,
+    };
+
+    let pos = mineral.pos();
+    let has_extractor = pos.look_for(look::STRUCTURES).iter()
+        .any(|s| s.structure_type() == StructureType::Extractor);
+    let extractor_site = pos.look_for(look::CONSTRUCTION_SITES).iter()
+        .any(|s| s.structure_type() == StructureType::Extractor);
+    if !has_extractor && !extractor_site {
+        info!("placing extractor in room {}", room.name());
+        room.create_construction_site(&pos, StructureType::Extractor);
+    }
+
+    if container(&mineral).is_none() && !container_site_placed(&mineral) {
+        if let Some(spot) = container_spot(room, &mineral) {
+            debug!("placing mineral container at {} in room {}", spot, room.name());
+            room.create_construction_site(&spot, StructureType::Container);
+        }
+    }
+}
+
+/// Gets the room's mineral if it should be mined right now: there's a built extractor,
+/// the mineral isn't regenerating, and we aren't holding more than the cap already
+pub fn minable(room: &Room) -> Option<Mineral> {
+    if room.controller().map(|c| c.level()).unwrap_or(0) < EXTRACTOR_RCL {
+        return None;
+    }
+    let mineral = room.find(find::MINERALS).into_iter().next()?;
+
+    let has_extractor = mineral.pos().look_for(look::STRUCTURES).iter()
+        .any(|s| s.structure_type() == StructureType::Extractor);
+    if !has_extractor || util::mineral_amount(&mineral) == 0 || util::mineral_regeneration(&mineral) > 0 {
+        return None;
+    }
+
+    let resource = mineral.mineral_type();
+    let stored = room.storage().map(|s| s.store_of(resource)).unwrap_or(0)
+        + room.terminal().map(|t| t.store_of(resource)).unwrap_or(0);
+    if stored >= config::u32_or("mineral.stock_cap", 100_000) {
+        trace!("not mining {} in room {}: {} stored", util::resource_name(resource), room.name(), stored);
+        return None;
+    }
+
+    Some(mineral)
+}
+
+/// Gets the mineral the room should spawn a miner for, if it doesn't have one already
+pub fn needs_miner(room: &Room) -> Option<Mineral> {
+    let mineral = minable(room)?;
+    if util::count_creeps(MineralMiner::role(), "mineral", &mineral.id().to_string()) == 0 {
+        Some(mineral)
+    } else {
+        None
+    }
+}
+
+/// Gets the position of the container next to the mineral, if it's been built
+pub fn container(mineral: &Mineral) -> Option<Position> {
+    mineral.pos().find_in_range(find::STRUCTURES, 1).into_iter()
+        .find(|s| s.structure_type() == StructureType::Container)
+        .map(|s| s.pos())
+}
+
+fn container_site_placed(mineral: &Mineral) -> bool {
+    mineral.pos().find_in_range(find::CONSTRUCTION_SITES, 1).iter()
+        .any(|s| s.structure_type() == StructureType::Container)
+}
+
+/// Picks the open tile next to the mineral closest to where its output ends up
+fn container_spot(room: &Room, mineral: &Mineral) -> Option<Position> {
+    let anchor = room.storage().map(|s| s.pos())
+        .or_else(|| room.find(find::MY_SPAWNS).first().map(|s| s.pos()))?;
+    let terrain = room.get_terrain();
+    let (mx, my) = (mineral.pos().x(), mineral.pos().y());
+
+    (mx - 1..=mx + 1)
+        .flat_map(|x| (my - 1..=my + 1).map(move |y| (x, y)))
+        .filter(|(x, y)| (*x, *y) != (mx, my) && terrain.get(*x, *y) != Terrain::Wall)
+        .map(|(x, y)| Position::new(x, y, room.name()))
+        .min_by_key(|p| p.get_range_to(&anchor))
+}
+
@@ -2,5 +2,21 @@
 //! Handles control & details for a single room
 //!
 
+pub mod census;
+pub mod defense;
+pub mod downgrade;
+pub mod factory;
+pub mod invader;
+pub mod labs;
+pub mod logistics;
+pub mod mineral;
+pub mod nuke;
+pub mod observer;
+pub mod powerspawn;
+pub mod recovery;
+pub mod remote;
 pub mod roomctl;
-pub mod source;
\ No newline at end of file
+pub mod safemode;
+pub mod signing;
+pub mod source;
+pub mod stock;
\ No newline at end of file
@@ -0,0 +1,171 @@
+//!
+//! Incoming nuke detection & response
+//!
+//! Structures caught in a blast get a rampart over them, which builders reinforce until it
+//! can soak up the damage. Targets are kept in `Memory.rooms.<name>.reinforce`, keyed by
+//! packed position, while nukes are in flight.
+//!
+
+use std::collections::HashMap;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find, look};
+use screeps::{Attackable, Position, Room, Structure, StructureRampart, StructureType};
+use screeps::constants::*;
+use screeps::memory;
+
+use crate::util::config;
+
+
+/// Structures worth protecting from a blast
+const PROTECTED_STRUCTURES: [StructureType; 9] = [
+    StructureType::Spawn,
+    StructureType::Storage,
+    StructureType::Terminal,
+    StructureType::Tower,
+    StructureType::Lab,
+    StructureType::Factory,
+    StructureType::PowerSpawn,
+    StructureType::Nuker,
+    StructureType::Observer,
+];
+
+/// Radius around the landing position that takes damage
+const BLAST_RADIUS: u32 = 2;
+
+
+/// Damage a nuke deals to a position at the given range from where it lands
+pub fn blast_damage(range: u32) -> u32 {
+    match range {
+        0 => NUKE_DAMAGE_RANGE_0,
+        r if r <= BLAST_RADIUS => NUKE_DAMAGE_RANGE_2,
+        _ => 0,
+    }
+}
+
+/// Scans an owned room for incoming nukes, alerting on new ones and queueing
+/// reinforcement of the ramparts over anything important in the blast
+pub fn scan(room: &Room) {
+    if !matches!(room.controller(), Some(c) if c.my()) {
+        return;
+    }
+
+    let nukes = room.find(find::NUKES);
+    let reinforce_path = format!("rooms.{}.reinforce", room.name());
+    let alerted_path = format!("rooms.{}.nukes", room.name());
+
+    if nukes.is_empty() {
+        memory::root().path_del(&reinforce_path);
+        memory::root().path_del(&alerted_path);
+        return;
+    }
+
+    for nuke in nukes.iter() {
+        let id_path = format!("{}.{}", alerted_path, nuke.id());
+        if memory::root().path_i32(&id_path).ok().flatten().is_none() {
+            let land_tick = screeps::game::time() + nuke.time_to_land();
+            let msg = format!(
+                "Nuke from {} incoming in room {} at {},{}: lands in {} ticks (tick {})",
+                nuke.launch_room_name(), room.name(), nuke.pos().x(), nuke.pos().y(),
+                nuke.time_to_land(), land_tick,
+            );
+            error!("{}", msg);
+            screeps::game::notify(&msg, None);
+            memory::root().path_set(&id_path, land_tick);
+        }
+    }
+
+    // total damage each protected structure will take, from all nukes
+    let mut damage: HashMap<Position, u32> = HashMap::new();
+    for nuke in nukes.iter() {
+        for s in nuke.pos().find_in_range(find::MY_STRUCTURES, BLAST_RADIUS) {
+            if PROTECTED_STRUCTURES.contains(&s.structure_type()) {
+                let dmg = blast_damage(s.pos().get_range_to(&nuke.pos()));
+                *damage.entry(s.pos()).or_insert(0) += dmg;
+            }
+        }
+    }
+
+    let max_hits = rampart_max_hits(room);
+    let margin = config::u32_or("nuke.reinforce_margin", 500_000);
+    for (pos, dmg) in damage.iter() {
+        let needed = dmg + margin;
+        if needed > max_hits {
+            warn!("rampart at {} in room {} can't be reinforced enough to survive ({} > {})",
+                pos, room.name(), needed, max_hits);
+        }
+
+        let has_rampart = pos.look_for(look::STRUCTURES).iter()
+            .any(|s| s.structure_type() == StructureType::Rampart);
+        if !has_rampart && pos.look_for(look::CONSTRUCTION_SITES).is_empty() {
+            debug!("placing rampart at {} in room {} ahead of nuke", pos, room.name());
+            room.create_construction_site(pos, StructureType::Rampart);
+        }
+
+        memory::root().path_set(
+            &format!("{}.{}", reinforce_path, pos.packed_repr()),
+            needed.min(max_hits),
+        );
+    }
+}
+
+/// Picks the rampart most in need of reinforcement before a nuke lands, if any
+pub fn reinforce_target(room: &Room) -> Option<StructureRampart> {
+    short_ramparts(room).into_iter()
+        .min_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(_, r)| r)
+}
+
+/// Number of protected ramparts still short of the hits they need
+pub fn reinforcements_needed(room: &Room) -> u32 {
+    short_ramparts(room).len() as u32
+}
+
+/// Gets the queued ramparts that don't have their wanted hits yet, along with
+/// how far along they are
+fn short_ramparts(room: &Room) -> Vec<(f64, StructureRampart)> {
+    let targets = match memory::root().path_dict(&format!("rooms.{}.reinforce", room.name())) {
+        Ok(Some(t)) => t,
+        _ => return Vec::new(),
+    };
+
+    targets.keys().iter()
+        .filter_map(|key| {
+            let pos = Position::from_packed(key.parse::<i32>().ok()?);
+            let wanted = targets.i32(key).ok()?? as u32;
+            let rampart = pos.look_for(look::STRUCTURES).into_iter()
+                .filter_map(|s| match s {
+                    Structure::Rampart(r) => Some(r),
+                    _ => None,
+                })
+                .next()?;
+            if rampart.hits() < wanted {
+                Some((rampart.hits() as f64 / wanted as f64, rampart))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Whether new spawning should wait, because a nuke lands before a creep could finish spawning
+pub fn spawning_paused(room: &Room) -> bool {
+    let pause = config::u32_or("nuke.spawn_pause_ticks", CREEP_SPAWN_TIME * MAX_CREEP_SIZE);
+    room.find(find::NUKES).iter().any(|n| n.time_to_land() <= pause)
+}
+
+/// Most hits a rampart can have at the room's controller level
+fn rampart_max_hits(room: &Room) -> u32 {
+    match room.controller().map(|c| c.level()).unwrap_or(0) {
+        0 | 1 => 0,
+        2 => RAMPART_HITS_MAX_RCL2,
+        3 => RAMPART_HITS_MAX_RCL3,
+        4 => RAMPART_HITS_MAX_RCL4,
+        5 => RAMPART_HITS_MAX_RCL5,
+        6 => RAMPART_HITS_MAX_RCL6,
+        7 => RAMPART_HITS_MAX_RCL7,
+        _ => RAMPART_HITS_MAX_RCL8,
+    }
+}
@@ -0,0 +1,132 @@
+//!
+//! Observer scanning of the rooms around an RCL 8 room
+//!
+//! Rooms in range are queued in `Memory.rooms.<name>.observe_queue`, most overdue first,
+//! and one is observed each tick. The room being observed is kept in
+//! `Memory.rooms.<name>.observing` so its intel can be recorded on the following tick.
+//!
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Room, RoomName, Structure, StructureObserver};
+use screeps::constants::*;
+use screeps::memory;
+
+use crate::intel::{self, RoomIntel};
+use crate::util::{self, config};
+
+
+/// Priority given to rooms we know nothing about
+const UNKNOWN_PRIORITY: u32 = 1_000;
+
+/// Most rooms kept in an observer's queue
+const QUEUE_SIZE: usize = 50;
+
+/// Fewest ticks between rebuilding an observer's queue
+const PLAN_TICKS: u32 = 20;
+
+
+/// How overdue a room is for another look, in percent of its rescan interval.
+/// Highways and rooms with power banks, deposits or other players' rooms get looked at more often.
+pub fn priority(room_name: RoomName, intel: Option<&RoomIntel>, username: &str) -> u32 {
+    let intel = match intel {
+        Some(i) => i,
+        None => return UNKNOWN_PRIORITY,
+    };
+
+    let active = !intel.power_banks.is_empty()
+        || !intel.deposits.is_empty()
+        || intel.is_hostile(username);
+    let interval = if active {
+        config::u32_or("observer.active_ticks", 100)
+    } else if util::is_highway(room_name) {
+        config::u32_or("observer.highway_ticks", 500)
+    } else {
+        config::u32_or("observer.rescan_ticks", 3_000)
+    };
+
+    intel.age() * 100 / interval.max(1)
+}
+
+/// Runs the observer of an RCL 8 room: records last tick's observation, then observes the next room
+pub fn run(room: &Room, username: &str) {
+    if room.controller().map(|c| c.level()).unwrap_or(0) < 8 {
+        return;
+    }
+
+    let observer = room.find(find::MY_STRUCTURES).into_iter()
+        .filter_map(|s| match s.as_structure() {
+            Structure::Observer(o) => Some(o),
+            _ => None,
+        })
+        .next();
+    let observer = match observer {
+        Some(o) => o,
+        None => return,
+    };
+
+    let path = format!("rooms.{}", room.name());
+    if let Some(observed) = memory::root().path_string(&format!("{}.observing", path)).ok().flatten() {
+        match observed.parse::<RoomName>().ok().and_then(screeps::game::rooms::get) {
+            Some(observed) => intel::record_now(&observed),
+            None => debug!("observation of {} from {} didn't come through", observed, room.name()),
+        }
+        memory::root().path_del(&format!("{}.observing", path));
+    }
+
+    let mut queue: Vec<String> = memory::root()
+        .path_arr(&format!("{}.observe_queue", path))
+        .ok().flatten().unwrap_or_default();
+    if queue.is_empty() {
+        let time = screeps::game::time();
+        let planned = memory::root().path_i32(&format!("{}.observe_planned", path)).ok().flatten();
+        if matches!(planned, Some(t) if time - (t as u32) < PLAN_TICKS) {
+            return;
+        }
+        memory::root().path_set(&format!("{}.observe_planned", path), time);
+
+        queue = plan(room.name(), username).iter().map(|r| r.to_string()).collect();
+        if queue.is_empty() {
+            return;
+        }
+    }
+
+    let next = queue.remove(0);
+    memory::root().path_set(&format!("{}.observe_queue", path), queue);
+    if let Ok(target) = next.parse::<RoomName>() {
+        observe(&observer, target, &path);
+    }
+}
+
+/// Lists the overdue rooms in the observer's range, most overdue first
+fn plan(origin: RoomName, username: &str) -> Vec<RoomName> {
+    let range = OBSERVER_RANGE as i32;
+    let mut rooms: Vec<(u32, RoomName)> = Vec::new();
+    for dx in -range..=range {
+        for dy in -range..=range {
+            let name = origin + (dx, dy);
+            if name == origin || !screeps::game::map::is_room_available(name) {
+                continue;
+            }
+            let p = priority(name, RoomIntel::load(name).as_ref(), username);
+            if p >= 100 {
+                rooms.push((p, name));
+            }
+        }
+    }
+
+    rooms.sort_by_key(|(p, _)| std::cmp::Reverse(*p));
+    rooms.into_iter().take(QUEUE_SIZE).map(|(_, name)| name).collect()
+}
+
+fn observe(observer: &StructureObserver, target: RoomName, path: &str) {
+    let r = observer.observe_room(target);
+    if r == ReturnCode::Ok {
+        trace!("observing room {}", target);
+        memory::root().path_set(&format!("{}.observing", path), target.to_string());
+    } else {
+        warn!("couldn't observe room {}: {:?}", target, r);
+    }
+}
@@ -0,0 +1,79 @@
+//!
+//! Power processing at the power spawn of an RCL 8 room
+//!
+//! Processing turns power into global power level at 50 energy a unit, so it only runs
+//! while storage holds more than `power.process_energy`. Carriers keep the power spawn
+//! stocked through haul tasks.
+//!
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{ResourceType, ReturnCode, Room, Structure, StructurePowerSpawn};
+use screeps::constants::*;
+
+use crate::metrics;
+use crate::util::config;
+
+use super::logistics::{HaulTask, MIN_HAUL};
+
+
+/// Gets the room's power spawn, if it has one
+pub fn power_spawn(room: &Room) -> Option<StructurePowerSpawn> {
+    room.find(find::MY_STRUCTURES).into_iter()
+        .find_map(|s| match s.as_structure() {
+            Structure::PowerSpawn(p) => Some(p),
+            _ => None,
+        })
+}
+
+/// Whether the room has the energy to spare for processing power
+fn can_process(room: &Room) -> bool {
+    let level = room.controller().map(|c| c.level()).unwrap_or(0);
+    let energy = room.storage().map(|s| s.store_of(ResourceType::Energy)).unwrap_or(0);
+    level >= 8 && config::bool_or("power.process", true) && energy >= config::u32_or("power.process_energy", 200_000)
+}
+
+/// Processes a unit of power, when the room has energy to spare
+pub fn run(room: &Room) {
+    let power_spawn = match power_spawn(room) {
+        Some(p) => p,
+        None => return,
+    };
+    if !can_process(room) || power_spawn.power() == 0 || power_spawn.store_of(ResourceType::Energy) < POWER_SPAWN_ENERGY_RATIO {
+        return;
+    }
+
+    if power_spawn.process_power() == ReturnCode::Ok {
+        metrics::inc_power_processed(1);
+    }
+}
+
+/// Tasks keeping the power spawn stocked with power & energy from storage, while it's processing
+pub fn haul_tasks(room: &Room) -> Vec<HaulTask> {
+    let (power_spawn, storage) = match (power_spawn(room), room.storage()) {
+        (Some(p), Some(s)) if can_process(room) => (p, s),
+        _ => return Vec::new(),
+    };
+
+    let mut tasks = Vec::new();
+    // power comes in small loads, so top it up once it's half used
+    let power = (POWER_SPAWN_POWER_CAPACITY - power_spawn.power()).min(storage.store_of(ResourceType::Power));
+    if power >= POWER_SPAWN_POWER_CAPACITY / 2 {
+        tasks.push(HaulTask {
+            resource: ResourceType::Power,
+            from: storage.id().to_string(),
+            to: power_spawn.id().to_string(),
+            amount: power,
+        });
+    }
+    let energy = POWER_SPAWN_ENERGY_CAPACITY - power_spawn.store_of(ResourceType::Energy);
+    if energy >= MIN_HAUL * 10 {
+        tasks.push(HaulTask {
+            resource: ResourceType::Energy,
+            from: storage.id().to_string(),
+            to: power_spawn.id().to_string(),
+            amount: energy,
+        });
+    }
+    tasks
+}
@@ -0,0 +1,79 @@
+//!
+//! Colony bootstrap & recovery
+//!
+//! A room that's lost all its harvesters & miners with too little energy for a normal harvester
+//! could otherwise sit waiting on the spawn's slow regeneration. It goes into recovery instead:
+//! every idle spawn makes the cheapest harvester it can right away, each as big as the energy
+//! then allows, and nothing else is spawned until there are `recovery.harvesters` of them.
+//! From there the normal economy takes over, with renewal recycling the small harvesters for
+//! full sized ones one at a time as the extensions fill back up.
+//!
+//! The tick recovery started at is kept in `Memory.rooms.<name>.recovery`.
+//!
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{Room};
+use screeps::memory;
+
+use crate::ctl::creep::renewal;
+use crate::ctl::creep::types::{BasicHarvester, CreepInfo, RemoteMiner};
+use crate::metrics;
+use crate::util::config;
+
+use super::census::Census;
+
+
+fn path(room: &Room) -> String {
+    format!("rooms.{}.recovery", room.name())
+}
+
+/// Whether the room is recovering from losing its economy
+pub fn is_recovering(room: &Room) -> bool {
+    memory::root().path_i32(&path(room)).ok().flatten().is_some()
+}
+
+/// Harvesters the room needs before leaving recovery
+pub fn harvesters_wanted() -> u32 {
+    config::u32_or("recovery.harvesters", 3)
+}
+
+/// Whether the room has no creeps bringing in energy, and not the energy for a normal harvester
+fn collapsed(room: &Room, census: &Census) -> bool {
+    let harvester_cost = renewal::optimal_cost(BasicHarvester::role(), room).unwrap_or_else(BasicHarvester::cost);
+    census.active(room.name(), BasicHarvester::role()) == 0
+        && census.active(room.name(), RemoteMiner::role()) == 0
+        && room.energy_available() < harvester_cost
+}
+
+/// Puts an owned room into recovery when its economy has collapsed, and takes it out again
+/// once it has enough harvesters. Returns whether the room is recovering.
+pub fn update(room: &Room, census: &Census) -> bool {
+    if !matches!(room.controller(), Some(c) if c.my()) || !config::bool_or("recovery.enabled", true) {
+        return false;
+    }
+
+    let mem = memory::root();
+    let time = screeps::game::time();
+    match mem.path_i32(&path(room)).ok().flatten() {
+        None => {
+            if !collapsed(room, census) {
+                return false;
+            }
+            warn!("room {} has lost its economy with {} energy, recovering", room.name(), room.energy_available());
+            mem.path_set(&path(room), time);
+            metrics::inc_recoveries(1);
+        },
+        Some(started) => {
+            if census.active(room.name(), BasicHarvester::role()) >= harvesters_wanted() {
+                info!("room {} recovered after {} ticks", room.name(), time.saturating_sub(started as u32));
+                mem.path_del(&path(room));
+                return false;
+            }
+        },
+    }
+
+    metrics::inc_rooms_recovering(1);
+    true
+}
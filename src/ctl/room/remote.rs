@@ -0,0 +1,375 @@
+//!
+//! Remote energy mining in rooms next to our own
+//!
+//! Remote rooms are tracked in `Memory.remotes.<name>`, along with the owned room
+//! mining them and what we know of their sources. Candidates are judged from room intel.
+//!
+//! The ticks left on our reservation are recorded whenever a remote room is visible, and
+//! counted down from there while it isn't. A reserver is only sent once the reservation would
+//! run low by the time a new one could spawn & walk over, so the room never drops back to an
+//! unreserved room's smaller sources.
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find, pathfinder};
+use screeps::{Position, Room, RoomName};
+use screeps::constants::*;
+use screeps::memory;
+
+use crate::ctl::creep::lifecycle;
+use crate::ctl::creep::types::{CreepInfo, RemoteHauler, RemoteMiner, Reserver};
+use crate::intel::RoomIntel;
+use crate::util::{self, config};
+
+use super::defense;
+use super::invader;
+
+
+/// Where a remote room is in its life cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteState {
+    /// waiting on intel to decide whether the room is worth mining
+    Scouting,
+    /// being mined
+    Active,
+    /// shut down until hostiles leave
+    Hostile,
+    /// not suitable for mining
+    Rejected,
+}
+
+impl RemoteState {
+    fn as_str(self) -> &'static str {
+        match self {
+            RemoteState::Scouting => "scouting",
+            RemoteState::Active => "active",
+            RemoteState::Hostile => "hostile",
+            RemoteState::Rejected => "rejected",
+        }
+    }
+
+    fn from_str(s: &str) -> RemoteState {
+        match s {
+            "active" => RemoteState::Active,
+            "hostile" => RemoteState::Hostile,
+            "rejected" => RemoteState::Rejected,
+            _ => RemoteState::Scouting,
+        }
+    }
+}
+
+/// A source being mined in a remote room
+#[derive(Debug, Clone)]
+pub struct RemoteSource {
+    pub id: String,
+    pub pos: Position,
+    /// path cost from the home room's drop-off to the source
+    pub distance: u32,
+}
+
+/// A room mined from one of our own rooms
+#[derive(Debug, Clone)]
+pub struct RemoteRoom {
+    pub name: RoomName,
+    pub home: RoomName,
+    pub state: RemoteState,
+    pub sources: Vec<RemoteSource>,
+    /// ticks left on our reservation when last seen
+    pub reservation: u32,
+    /// tick the reservation was last seen at
+    pub reservation_seen: u32,
+}
+
+/// A creep a home room should spawn for its remote rooms
+#[derive(Debug, Clone)]
+pub enum RemoteSpawn {
+    Reserver(RoomName),
+    Miner(RoomName, RemoteSource),
+    /// hauler for a source, with the number of carry parts it should have
+    Hauler(RoomName, RemoteSource, u32),
+}
+
+
+impl RemoteRoom {
+    /// Loads a remote room from memory
+    pub fn load(name: RoomName) -> Option<RemoteRoom> {
+        let mem = memory::root().path_dict(&format!("remotes.{}", name)).ok()??;
+        let home = RoomName::from_str(&mem.string("home").ok()??).ok()?;
+        let state = RemoteState::from_str(&mem.string("state").ok()?.unwrap_or_default());
+
+        let sources = match mem.dict("sources").ok().flatten() {
+            Some(sources) => sources.keys().into_iter()
+                .filter_map(|id| {
+                    let src = sources.dict(&id).ok()??;
+                    Some(RemoteSource {
+                        pos: Position::from_packed(src.i32("pos").ok()??),
+                        distance: src.i32("distance").ok()?? as u32,
+                        id,
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let get = |key: &str| mem.i32(key).ok().flatten().unwrap_or(0) as u32;
+        Some(RemoteRoom {
+            name,
+            home,
+            state,
+            sources,
+            reservation: get("reservation"),
+            reservation_seen: get("reservation_seen"),
+        })
+    }
+
+    fn set_state(&mut self, state: RemoteState) {
+        if self.state != state {
+            info!("remote room {} (home {}): {:?} -> {:?}", self.name, self.home, self.state, state);
+        }
+        self.state = state;
+        memory::root().path_set(&format!("remotes.{}.state", self.name), state.as_str());
+    }
+
+    /// Whether creeps can safely work in the room
+    pub fn is_safe(&self) -> bool {
+        self.state != RemoteState::Hostile
+            && self.state != RemoteState::Rejected
+            && !invader::core_present(self.name)
+    }
+
+    /// Ticks our reservation has left, counted down from when it was last seen
+    pub fn reservation_left(&self) -> u32 {
+        let elapsed = screeps::game::time().saturating_sub(self.reservation_seen);
+        self.reservation.saturating_sub(elapsed)
+    }
+
+    /// Whether the reservation runs low before a reserver sent now could get there & take over
+    pub fn needs_reserver(&self) -> bool {
+        let parts = Reserver::parts().len() as u32 * Reserver::max_units();
+        let travel = util::travel_ticks(self.home, self.name);
+        let lead = lifecycle::replacement_ticks(parts, travel) + config::u32_or("remote.reserve_margin", 1000);
+        self.reservation_left() <= lead
+    }
+
+    /// Scores the room by its sources, closer sources scoring higher
+    pub fn score(&self) -> u32 {
+        self.sources.iter().fold(0, |score, s| score + 10_000 / (s.distance + 1))
+    }
+
+    /// Number of carry parts needed to keep up with a source's output over the round trip
+    pub fn carry_needed(source: &RemoteSource) -> u32 {
+        let per_tick = SOURCE_ENERGY_CAPACITY / ENERGY_REGEN_TIME;
+        per_tick * source.distance * 2 / CARRY_CAPACITY + 1
+    }
+}
+
+
+/// Gets all remote rooms mined from the given home room
+pub fn remotes_of(home: RoomName) -> Vec<RemoteRoom> {
+    all_remotes().into_iter().filter(|r| r.home == home).collect()
+}
+
+/// Gets all remote rooms we know about
+pub fn all_remotes() -> Vec<RemoteRoom> {
+    match memory::root().dict("remotes") {
+        Ok(Some(remotes)) => remotes.keys().into_iter()
+            .filter_map(|name| RoomName::from_str(&name).ok())
+            .filter_map(RemoteRoom::load)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether creeps can safely work in the remote room. Unknown rooms are considered safe.
+pub fn is_safe(room_name: RoomName) -> bool {
+    match RemoteRoom::load(room_name) {
+        Some(r) => r.is_safe(),
+        None => true,
+    }
+}
+
+
+/// Adds neutral rooms next to an owned room as remote candidates, up to the configured limit
+pub fn discover(home: &Room) {
+    let level = home.controller().map(|c| c.level()).unwrap_or(0);
+    if level < config::u32_or("remote.min_rcl", 3) {
+        return;
+    }
+
+    let max = config::u32_or("remote.max_rooms", 2) as usize;
+    let current = remotes_of(home.name()).into_iter()
+        .filter(|r| r.state != RemoteState::Rejected)
+        .count();
+    if current >= max {
+        return;
+    }
+
+    let owned: Vec<RoomName> = util::owned_rooms().iter().map(|r| r.name()).collect();
+    let candidate = screeps::game::map::describe_exits(home.name()).values()
+        .filter_map(|name| RoomName::from_str(name).ok())
+        .filter(|name| !util::is_highway(*name) && !util::is_source_keeper(*name))
+        .filter(|name| !owned.contains(name))
+        .find(|name| memory::root().path_dict(&format!("remotes.{}", name)).ok().flatten().is_none());
+
+    if let Some(name) = candidate {
+        info!("scouting {} as a remote room for {}", name, home.name());
+        let path = format!("remotes.{}", name);
+        memory::root().path_set(&format!("{}.home", path), home.name().to_string());
+        memory::root().path_set(&format!("{}.state", path), RemoteState::Scouting.as_str());
+    }
+}
+
+/// Updates whether a visible remote room is currently safe to work in
+pub fn scan(room: &Room, username: &str) {
+    let mut remote = match RemoteRoom::load(room.name()) {
+        Some(r) => r,
+        None => return,
+    };
+
+    let hostile_ttl = room.find(find::HOSTILE_CREEPS).iter()
+        .filter(|c| {
+            let (melee, ranged, _) = defense::combat_power(c);
+            melee + ranged > 0
+        })
+        .map(|c| c.ticks_to_live())
+        .max();
+
+    let path = format!("remotes.{}", room.name());
+    match remote.state {
+        RemoteState::Rejected => (),
+        _ if hostile_ttl.is_some() => {
+            if remote.state != RemoteState::Hostile {
+                warn!("hostiles in remote room {}, shutting it down", room.name());
+            }
+            let until = screeps::game::time() + hostile_ttl.unwrap_or(0).min(CREEP_LIFE_TIME);
+            memory::root().path_set(&format!("{}.hostile_until", path), until);
+            remote.set_state(RemoteState::Hostile);
+        },
+        RemoteState::Hostile => {
+            // no hostiles in sight anymore
+            let state = if remote.sources.is_empty() { RemoteState::Scouting } else { RemoteState::Active };
+            remote.set_state(state);
+        },
+        RemoteState::Scouting | RemoteState::Active => (),
+    }
+
+    if let Some(controller) = room.controller() {
+        let ticks = match util::reservation(&controller) {
+            Some((u, ticks)) if u == username => ticks,
+            _ => 0,
+        };
+        memory::root().path_set(&format!("{}.reservation", path), ticks);
+        memory::root().path_set(&format!("{}.reservation_seen", path), screeps::game::time());
+    }
+}
+
+/// Decides on rooms still being scouted once there's intel on them, and lifts hostile
+/// shutdowns that have run their course in rooms we can't see, so they get checked again
+pub fn refresh(username: &str) {
+    let time = screeps::game::time();
+    for mut remote in all_remotes() {
+        if remote.state == RemoteState::Scouting {
+            if let Some(intel) = RoomIntel::load(remote.name) {
+                evaluate(&mut remote, &intel, username);
+            }
+        } else if remote.state == RemoteState::Hostile && screeps::game::rooms::get(remote.name).is_none() {
+            let until = memory::root()
+                .path_i32(&format!("remotes.{}.hostile_until", remote.name))
+                .ok().flatten().unwrap_or(0) as u32;
+            if time >= until {
+                remote.set_state(RemoteState::Scouting);
+            }
+        }
+    }
+}
+
+/// Decides whether a scouted room is worth mining, and records its sources
+fn evaluate(remote: &mut RemoteRoom, intel: &RoomIntel, username: &str) {
+    let taken = intel.owner.is_some() || intel.is_hostile(username);
+    if !intel.has_controller || taken || !intel.towers.is_empty() {
+        remote.set_state(RemoteState::Rejected);
+        return;
+    }
+
+    let home = match screeps::game::rooms::get(remote.home) {
+        Some(h) => h,
+        None => return,
+    };
+    let origin = match drop_off(&home) {
+        Some(o) => o,
+        None => return,
+    };
+
+    let path = format!("remotes.{}.sources", remote.name);
+    let max_distance = config::u32_or("remote.max_distance", 120);
+    let mut sources = Vec::new();
+    for (id, pos) in intel.sources.iter() {
+        let res = pathfinder::search(&origin, pos, 1, pathfinder::SearchOptions::new().max_rooms(4));
+        if res.incomplete || res.cost > max_distance {
+            debug!("skipping source {} in {}: too far", id, remote.name);
+            continue;
+        }
+
+        memory::root().path_set(&format!("{}.{}.pos", path, id), pos.packed_repr());
+        memory::root().path_set(&format!("{}.{}.distance", path, id), res.cost);
+        sources.push(RemoteSource { id: id.clone(), pos: *pos, distance: res.cost });
+    }
+
+    if sources.is_empty() {
+        remote.set_state(RemoteState::Rejected);
+    } else {
+        info!("remote room {} accepted with {} sources", remote.name, sources.len());
+        remote.sources = sources;
+        remote.set_state(RemoteState::Active);
+    }
+}
+
+/// Where energy from remote rooms is brought to in the home room
+pub fn drop_off(home: &Room) -> Option<Position> {
+    match home.storage() {
+        Some(storage) => Some(storage.pos()),
+        None => home.find(find::MY_SPAWNS).first().map(|s| s.pos()),
+    }
+}
+
+
+/// Picks the next creep the home room needs for its remote rooms, if any
+pub fn next_spawn(home: &Room) -> Option<RemoteSpawn> {
+    let capacity = home.energy_capacity_available();
+    let max_haul_units = (capacity / RemoteHauler::cost()).clamp(1, MAX_CREEP_SIZE / 2);
+
+    // staff the best rooms & the closest sources first
+    let mut remotes = remotes_of(home.name());
+    remotes.sort_by_key(|r| std::cmp::Reverse(r.score()));
+
+    for mut remote in remotes {
+        remote.sources.sort_by_key(|s| s.distance);
+        if remote.state != RemoteState::Active || !remote.is_safe() {
+            continue;
+        }
+
+        let name = remote.name.to_string();
+        if util::count_creeps(Reserver::role(), "target", &name) == 0 && remote.needs_reserver() {
+            return Some(RemoteSpawn::Reserver(remote.name));
+        }
+
+        for source in remote.sources.iter() {
+            if util::count_creeps(RemoteMiner::role(), "source", &source.id) == 0 {
+                return Some(RemoteSpawn::Miner(remote.name, source.clone()));
+            }
+
+            let carry = RemoteRoom::carry_needed(source);
+            let haulers = util::count_creeps(RemoteHauler::role(), "source", &source.id);
+            let wanted = carry.div_ceil(max_haul_units);
+            if haulers < wanted {
+                return Some(RemoteSpawn::Hauler(remote.name, source.clone(), carry.min(max_haul_units)));
+            }
+        }
+    }
+
+    None
+}
@@ -5,54 +5,227 @@
 
 use log::*;
 
-use screeps::{find, Room, RoomName};
+use screeps::prelude::*;
+use screeps::{find, Mineral, Room, RoomName, StructureSpawn};
 
-use crate::ctl::creep::{builder, harvester};
+use crate::ctl::creep::{attacker, builder, carrier, claimer, defender, harvester, hauler, miner, pioneer, powerbank, reserver, scout, upgrader};
+use crate::ctl::creep::types::{BasicBuilder, BasicHarvester, Carrier, CreepInfo, HealerDefender, MeleeDefender, MineralMiner, RangedDefender, Scout, Upgrader};
+use crate::ctl::shard::deposit::{self, DepositSpawn};
+use crate::ctl::shard::expansion::{self, ExpansionSpawn};
+use crate::ctl::shard::intel;
+use crate::ctl::shard::power::{self, PowerSpawn};
+
+use super::census::Census;
+use super::defense::ThreatAssessment;
+use super::downgrade;
+use super::invader::{self, CoreSighting};
+use super::logistics;
+use super::mineral;
+use super::nuke;
+use super::recovery;
+use super::remote::{self, RemoteSpawn};
+use super::safemode::{self, SafeModePolicy};
+use super::stock;
 
 
 /// Manages a room and its contents, including creeps, spawning, construction, and more
 pub struct RoomCtl<'a> {
     name: RoomName,
-    room: &'a Room
+    room: &'a Room,
+    census: &'a Census,
 }
 
-impl RoomCtl<'_> {
-    pub fn new(room: &Room) -> RoomCtl {
+impl<'a> RoomCtl<'a> {
+    pub fn new(room: &'a Room, census: &'a Census) -> RoomCtl<'a> {
         RoomCtl {
             name: room.name(),
-            room
+            room,
+            census,
+        }
+    }
+
+    /// Spawns that aren't busy spawning a creep
+    fn idle_spawns(&self) -> Vec<StructureSpawn> {
+        self.room.find(find::MY_SPAWNS).into_iter()
+            .filter(|s| s.spawning().is_none())
+            .collect()
+    }
+
+    /// Builds the room's spawn queue, highest priority first, with one item per idle spawn so
+    /// two spawns don't both fill the same need on one tick. Needs counted in creeps can take
+    /// several spawns at once, the rest only one. Needs further down aren't looked at once
+    /// every idle spawn has an item. Queued items are recorded in the census.
+    pub fn spawn_queue(&self, threat: ThreatAssessment, time: u32) -> Vec<SpawnStrategy> {
+        let idle = self.idle_spawns().len();
+        let harvesters = self.creeps_with_role(BasicHarvester::role());
+        if recovery::is_recovering(self.room) {
+            let queue: Vec<SpawnStrategy> = (harvesters..recovery::harvesters_wanted())
+                .take(idle)
+                .map(|_| SpawnStrategy::Recovery)
+                .collect();
+            for _ in queue.iter() {
+                self.census.queue(self.name, BasicHarvester::role());
+            }
+            return queue;
+        }
+
+        let builders = self.creeps_with_role(BasicBuilder::role());
+        let needs: [&dyn Fn() -> Vec<SpawnStrategy>; 13] = [
+            &|| self.downgrade_spawn().into_iter().collect(),
+            &|| if self.needs_defenders(&threat) { vec![SpawnStrategy::Defense(threat)] } else { vec![] },
+            &|| self.core_to_attack().map(SpawnStrategy::CoreAttack).into_iter().collect(),
+            &|| (harvesters..self.energy_spots(true)).map(|_| SpawnStrategy::CtrlrUpgrade).collect(),
+            &|| {
+                let wanted = self.construction_sites() / 2 + self.reinforcements_needed().min(3);
+                (builders..wanted).map(|_| SpawnStrategy::Builders).collect()
+            },
+            &|| self.expansion_spawn().map(SpawnStrategy::Expansion).into_iter().collect(),
+            &|| self.power_spawn().map(SpawnStrategy::Power).into_iter().collect(),
+            &|| self.deposit_spawn().map(SpawnStrategy::Deposit).into_iter().collect(),
+            &|| self.remote_spawn().map(SpawnStrategy::Remote).into_iter().collect(),
+            &|| if self.needs_carrier() { vec![SpawnStrategy::Carrier] } else { vec![] },
+            &|| if self.needs_upgrader() { vec![SpawnStrategy::Upgrader] } else { vec![] },
+            &|| self.mineral_to_mine().map(SpawnStrategy::MineralMiner).into_iter().collect(),
+            &|| if time % 50 == 17 && self.is_owned() && self.needs_scout() { vec![SpawnStrategy::Scout] } else { vec![] },
+        ];
+
+        let mut queue = Vec::new();
+        for need in needs.iter() {
+            if queue.len() >= idle {
+                break;
+            }
+            queue.extend(need());
+        }
+        queue.truncate(idle);
+        for role in queue.iter().filter_map(SpawnStrategy::role) {
+            self.census.queue(self.name, role);
         }
+        queue
     }
 
-    /// Manage all spawns, running required spawning strategy
-    pub fn manage_spawns(&self, strategy: SpawnStrategy) {
-        for spawn in self.room.find(find::MY_SPAWNS).iter() {
+    /// Manage all spawns, handing each idle spawn the next item of the queue, highest priority first
+    pub fn manage_spawns(&self, queue: Vec<SpawnStrategy>) {
+        if nuke::spawning_paused(self.room) {
+            info!("spawning paused in room {}, nuke about to land", self.name);
+            return;
+        }
+
+        for (spawn, strategy) in self.idle_spawns().iter().zip(queue.iter()) {
             debug!("running spawn {}", spawn.name());
-            
-            match strategy {
-                SpawnStrategy::CtrlrUpgrade => {
-                    // // determine if we already have the max supported number of energy harvesters
-                    // let spots = self.energy_spots(true);
-                    // if spots as usize > screeps::game::creeps::keys().len() {
-                    //     if let Err(e) = harvester::spawn_basic_harvester(&spawn) {
-                    //         warn!("Failed to create basic harvester: {}", e);
-                    //     }
-                    // } else {
-                    //     debug!("Skipping spawn; only {} energy spots", spots);
-                    // }
-                    if let Err(e) = harvester::spawn_basic_harvester(&spawn) {
-                        warn!("Failed to create basic harvester: {}", e);
-                    }
-                },
-                SpawnStrategy::Builders => {
-                    if let Err(e) = builder::spawn_basic_builder(&spawn) {
-                        warn!("Failed to create basic builder: {}", e);
-                    }
+            self.spawn(spawn, strategy);
+        }
+    }
+
+    /// Runs one spawning strategy on a spawn
+    fn spawn(&self, spawn: &StructureSpawn, strategy: &SpawnStrategy) {
+        match *strategy {
+            SpawnStrategy::CtrlrUpgrade => {
+                // // determine if we already have the max supported number of energy harvesters
+                // let spots = self.energy_spots(true);
+                // if spots as usize > screeps::game::creeps::keys().len() {
+                //     if let Err(e) = harvester::spawn_basic_harvester(&spawn) {
+                //         warn!("Failed to create basic harvester: {}", e);
+                //     }
+                // } else {
+                //     debug!("Skipping spawn; only {} energy spots", spots);
+                // }
+                if let Err(e) = harvester::spawn_basic_harvester(spawn) {
+                    warn!("Failed to create basic harvester: {}", e);
+                }
+            },
+            SpawnStrategy::Recovery => {
+                if let Err(e) = harvester::spawn_recovery_harvester(spawn) {
+                    warn!("Failed to create recovery harvester: {}", e);
                 }
-                _ => {
-                    warn!("Unknown spawn strategy for room {}", self.name)
+            },
+            SpawnStrategy::Builders => {
+                if let Err(e) = builder::spawn_basic_builder(spawn) {
+                    warn!("Failed to create basic builder: {}", e);
+                }
+            },
+            SpawnStrategy::Defense(ref threat) => {
+                // fill in whichever defender type is missing, frontline first
+                let (melee, ranged, healers) = threat.defenders_wanted();
+                let res = if self.creeps_with_role(MeleeDefender::role()) < melee {
+                    defender::spawn_defender::<MeleeDefender>(spawn, threat)
+                } else if self.creeps_with_role(RangedDefender::role()) < ranged {
+                    defender::spawn_defender::<RangedDefender>(spawn, threat)
+                } else if self.creeps_with_role(HealerDefender::role()) < healers {
+                    defender::spawn_defender::<HealerDefender>(spawn, threat)
+                } else {
+                    Ok(())
+                };
+
+                if let Err(e) = res {
+                    warn!("Failed to create defender: {}", e);
+                }
+            },
+            SpawnStrategy::CoreAttack(ref core) => {
+                if let Err(e) = attacker::spawn_core_attacker(spawn, core) {
+                    warn!("Failed to create core attacker: {}", e);
+                }
+            },
+            SpawnStrategy::Remote(ref job) => {
+                let res = match job {
+                    RemoteSpawn::Reserver(target) => reserver::spawn_reserver(spawn, *target),
+                    RemoteSpawn::Miner(_, source) => miner::spawn_remote_miner(spawn, source),
+                    RemoteSpawn::Hauler(_, source, carry) => hauler::spawn_remote_hauler(spawn, source, *carry),
+                };
+                if let Err(e) = res {
+                    warn!("Failed to create remote creep: {}", e);
+                }
+            },
+            SpawnStrategy::Expansion(ref job) => {
+                let res = match job {
+                    ExpansionSpawn::Claimer(target) => claimer::spawn_claimer(spawn, *target),
+                    ExpansionSpawn::Pioneer(target) => pioneer::spawn_pioneer(spawn, *target),
+                };
+                if let Err(e) = res {
+                    warn!("Failed to create expansion creep: {}", e);
+                }
+            },
+            SpawnStrategy::Power(ref job) => {
+                let res = match job {
+                    PowerSpawn::Attacker(op) => powerbank::spawn_power_attacker(spawn, op),
+                    PowerSpawn::Healer(op) => powerbank::spawn_power_healer(spawn, op),
+                    PowerSpawn::Hauler(op) => powerbank::spawn_power_hauler(spawn, op),
+                };
+                if let Err(e) = res {
+                    warn!("Failed to create power creep: {}", e);
+                }
+            },
+            SpawnStrategy::Deposit(ref job) => {
+                let res = match job {
+                    DepositSpawn::Harvester(op) => miner::spawn_deposit_harvester(spawn, op),
+                    DepositSpawn::Hauler(op) => hauler::spawn_deposit_hauler(spawn, op),
+                };
+                if let Err(e) = res {
+                    warn!("Failed to create deposit creep: {}", e);
+                }
+            },
+            SpawnStrategy::Carrier => {
+                if let Err(e) = carrier::spawn_carrier(spawn) {
+                    warn!("Failed to create carrier: {}", e);
+                }
+            },
+            SpawnStrategy::Upgrader => {
+                if let Err(e) = upgrader::spawn_upgrader(spawn) {
+                    warn!("Failed to create upgrader: {}", e);
+                }
+            },
+            SpawnStrategy::MineralMiner(ref target) => {
+                if let Err(e) = miner::spawn_mineral_miner(spawn, target) {
+                    warn!("Failed to create mineral miner: {}", e);
+                }
+            },
+            SpawnStrategy::Scout => {
+                if let Err(e) = scout::spawn_scout(spawn) {
+                    warn!("Failed to create scout: {}", e);
                 }
             }
+            _ => {
+                warn!("Unknown spawn strategy for room {}", self.name)
+            }
         }
     }
 
@@ -75,6 +248,100 @@ impl RoomCtl<'_> {
         }
     }
 
+    /// Whether we own the room's controller
+    pub fn is_owned(&self) -> bool {
+        matches!(self.room.controller(), Some(c) if c.my())
+    }
+
+    /// Assesses the threat posed by hostiles currently in the room
+    pub fn threat(&self) -> ThreatAssessment {
+        ThreatAssessment::assess(self.room)
+    }
+
+    /// Determines whether the room is missing any of the defenders it needs for a threat
+    pub fn needs_defenders(&self, threat: &ThreatAssessment) -> bool {
+        let (melee, ranged, healers) = threat.defenders_wanted();
+        self.creeps_with_role(MeleeDefender::role()) < melee
+            || self.creeps_with_role(RangedDefender::role()) < ranged
+            || self.creeps_with_role(HealerDefender::role()) < healers
+    }
+
+    /// Picks a creep to keep the controller from downgrading, when it's at risk & nothing's upgrading it
+    pub fn downgrade_spawn(&self) -> Option<SpawnStrategy> {
+        if !downgrade::needs_creep(self.room, self.census) {
+            return None;
+        }
+        warn!("controller in room {} about to downgrade with nothing to upgrade it", self.name);
+        if downgrade::upgrader_has_energy(self.room) {
+            Some(SpawnStrategy::Upgrader)
+        } else {
+            Some(SpawnStrategy::CtrlrUpgrade)
+        }
+    }
+
+    /// Finds an invader core this room should send attackers after, if any
+    pub fn core_to_attack(&self) -> Option<CoreSighting> {
+        invader::sightings().into_iter()
+            .filter(|core| core.should_attack())
+            .filter(|core| invader::responder_for(core.room) == Some(self.name))
+            .find(|core| attacker::core_attackers_for(core.room) == 0)
+    }
+
+    /// Finds the next creep needed to work this room's remote rooms, if any
+    pub fn remote_spawn(&self) -> Option<RemoteSpawn> {
+        remote::next_spawn(self.room)
+    }
+
+    /// Finds the next creep this room should send to a room being expanded into, if any
+    pub fn expansion_spawn(&self) -> Option<ExpansionSpawn> {
+        expansion::next_spawn(self.room)
+    }
+
+    /// Finds the next creep this room should send to a power bank, if any
+    pub fn power_spawn(&self) -> Option<PowerSpawn> {
+        power::next_spawn(self.room)
+    }
+
+    /// Finds the next creep this room should send to a deposit, if any
+    pub fn deposit_spawn(&self) -> Option<DepositSpawn> {
+        deposit::next_spawn(self.room)
+    }
+
+    /// Whether the room is short of carriers for the hauling it has waiting
+    pub fn needs_carrier(&self) -> bool {
+        self.creeps_with_role(Carrier::role()) < logistics::carriers_wanted(self.room)
+    }
+
+    /// Whether the room has storage energy to spare for another upgrader
+    pub fn needs_upgrader(&self) -> bool {
+        self.creeps_with_role(Upgrader::role()) < stock::upgraders_wanted(self.room)
+    }
+
+    /// Finds the room's mineral if it needs a miner working it
+    pub fn mineral_to_mine(&self) -> Option<Mineral> {
+        mineral::needs_miner(self.room)
+    }
+
+    /// Whether the room should send out a scout to refresh the intel around it
+    pub fn needs_scout(&self) -> bool {
+        intel::needs_scout(self.name)
+    }
+
+    /// Activates safe mode if the threat gets out of hand
+    pub fn guard_safe_mode(&self, threat: &ThreatAssessment, policy: &SafeModePolicy) {
+        safemode::guard(self.room, threat, policy);
+    }
+
+    /// Counts the creeps spawned for this room with the given role, as the census has them
+    pub fn creeps_with_role(&self, role: &str) -> u32 {
+        self.census.active(self.name, role)
+    }
+
+    /// Determines how many ramparts need reinforcing ahead of incoming nukes
+    pub fn reinforcements_needed(&self) -> u32 {
+        nuke::reinforcements_needed(self.room)
+    }
+
     /// Determines how many construction sites are in the room
     pub fn construction_sites(&self) -> u32 {
         self.room.find(find::CONSTRUCTION_SITES).len() as u32
@@ -85,6 +352,43 @@ impl RoomCtl<'_> {
 pub enum SpawnStrategy {
     /// Create builders
     Builders,
+    /// Create the defenders needed to fight off a threat
+    Defense(ThreatAssessment),
+    /// Create attackers to take down an invader core
+    CoreAttack(CoreSighting),
+    /// Create reservers, miners and haulers for remote rooms
+    Remote(RemoteSpawn),
+    /// Create claimers and pioneers for a room being expanded into
+    Expansion(ExpansionSpawn),
+    /// Create attackers, healers and haulers for a power bank
+    Power(PowerSpawn),
+    /// Create harvesters and haulers for a deposit
+    Deposit(DepositSpawn),
+    /// Create a carrier to work through the room's hauling
+    Carrier,
+    /// Create an upgrader to spend surplus storage energy
+    Upgrader,
+    /// Create a miner for the room's mineral
+    MineralMiner(Mineral),
+    /// Create a scout to refresh intel on nearby rooms
+    Scout,
     /// Focus exclusively on spawning harvester creeps to upgrade the controller
     CtrlrUpgrade,
-}
\ No newline at end of file
+    /// Create the cheapest harvesters that work, to bring a room's economy back
+    Recovery,
+}
+
+impl SpawnStrategy {
+    /// Role the strategy spawns, for strategies that always spawn the same one
+    pub fn role(&self) -> Option<&'static str> {
+        match self {
+            SpawnStrategy::CtrlrUpgrade | SpawnStrategy::Recovery => Some(BasicHarvester::role()),
+            SpawnStrategy::Builders => Some(BasicBuilder::role()),
+            SpawnStrategy::Carrier => Some(Carrier::role()),
+            SpawnStrategy::Upgrader => Some(Upgrader::role()),
+            SpawnStrategy::MineralMiner(_) => Some(MineralMiner::role()),
+            SpawnStrategy::Scout => Some(Scout::role()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//!
+//! Last-resort safe mode activation for rooms under attack
+//!
+
+use std::collections::VecDeque;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Creep, ReturnCode, Room, Structure, StructureController, Terrain};
+
+use crate::metrics;
+use crate::util::config;
+
+use super::defense::{self, ThreatAssessment, ThreatLevel};
+
+
+/// Policy deciding when safe mode may be activated, read from `Memory.config.safe_mode`
+#[derive(Debug)]
+pub struct SafeModePolicy {
+    /// whether safe mode should be activated automatically at all
+    pub enabled: bool,
+    /// log what would happen instead of activating safe mode
+    pub dry_run: bool,
+    /// extra tiles beyond attack range at which a hostile is about to damage a spawn
+    pub spawn_margin: u32,
+    /// activate when hostiles are inside the ramparts and the towers can't keep up
+    pub on_breach: bool,
+}
+
+impl SafeModePolicy {
+    /// Loads the policy from memory, using defaults for anything not configured
+    pub fn load() -> SafeModePolicy {
+        SafeModePolicy {
+            enabled: config::bool_or("safe_mode.enabled", true),
+            dry_run: config::bool_or("safe_mode.dry_run", false),
+            spawn_margin: config::u32_or("safe_mode.spawn_margin", 1),
+            on_breach: config::bool_or("safe_mode.on_breach", true),
+        }
+    }
+}
+
+/// Reasons to activate safe mode
+#[derive(Debug, PartialEq, Eq)]
+pub enum Trigger {
+    /// an armed hostile is about to reach an unprotected spawn
+    SpawnThreatened,
+    /// armed hostiles got past the ramparts, and the towers aren't enough to stop them
+    RampartsBreached,
+}
+
+
+/// Activates safe mode in the room if the policy allows it and the situation calls for it
+pub fn guard(room: &Room, threat: &ThreatAssessment, policy: &SafeModePolicy) {
+    if !policy.enabled || threat.level < ThreatLevel::Medium {
+        return;
+    }
+
+    let controller = match room.controller() {
+        Some(c) if c.my() => c,
+        _ => return,
+    };
+
+    let trigger = match check(room, threat, policy) {
+        Some(t) => t,
+        None => return,
+    };
+
+    if let Err(reason) = available(&controller) {
+        warn!("room {} needs safe mode ({:?}), but it's unavailable: {}", room.name(), trigger, reason);
+        return;
+    }
+
+    if policy.dry_run {
+        warn!("[dry run] would activate safe mode in room {} ({:?})", room.name(), trigger);
+        return;
+    }
+
+    match controller.activate_safe_mode() {
+        ReturnCode::Ok => {
+            error!("activated safe mode in room {} ({:?})", room.name(), trigger);
+            metrics::inc_safe_modes(1);
+        },
+        r => warn!("couldn't activate safe mode in room {}: {:?}", room.name(), r),
+    }
+}
+
+/// Checks whether the controller is able to activate safe mode right now
+fn available(controller: &StructureController) -> Result<(), String> {
+    if controller.safe_mode().is_some() {
+        Err("already active".to_string())
+    } else if controller.safe_mode_available() == 0 {
+        Err("no activations available".to_string())
+    } else if let Some(cooldown) = controller.safe_mode_cooldown() {
+        Err(format!("on cooldown for {} ticks", cooldown))
+    } else if controller.upgrade_blocked().is_some() {
+        Err("controller is blocked".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// Determines whether the hostiles in the room warrant safe mode under the policy
+pub fn check(room: &Room, threat: &ThreatAssessment, policy: &SafeModePolicy) -> Option<Trigger> {
+    let armed: Vec<(Creep, u32, u32)> = room.find(find::HOSTILE_CREEPS).into_iter()
+        .filter_map(|c| {
+            let (melee, ranged, _) = defense::combat_power(&c);
+            if melee + ranged > 0 {
+                Some((c, melee, ranged))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    if armed.is_empty() {
+        return None;
+    }
+
+    let structures = room.find(find::STRUCTURES);
+    let ramparts: Vec<_> = structures.iter()
+        .filter_map(|s| match s {
+            Structure::Rampart(r) if r.my() => Some(r),
+            _ => None,
+        })
+        .collect();
+
+    // spawns without a rampart over them get damaged directly
+    let exposed_spawns = room.find(find::MY_SPAWNS).into_iter()
+        .filter(|s| !ramparts.iter().any(|r| r.pos().is_equal_to(s)));
+    for spawn in exposed_spawns {
+        let about_to_hit = armed.iter().any(|(c, melee, ranged)| {
+            let range = c.pos().get_range_to(&spawn);
+            (*melee > 0 && range <= 1 + policy.spawn_margin)
+                || (*ranged > 0 && range <= 3 + policy.spawn_margin)
+        });
+        if about_to_hit {
+            return Some(Trigger::SpawnThreatened);
+        }
+    }
+
+    if policy.on_breach && threat.level == ThreatLevel::High && !ramparts.is_empty() {
+        // anything the exits can't reach without crossing a rampart or wall is inside
+        let outside = reachable_from_exits(room, &structures);
+        let breached = armed.iter()
+            .any(|(c, _, _)| !outside[idx(c.pos().x(), c.pos().y())]);
+        if breached {
+            return Some(Trigger::RampartsBreached);
+        }
+    }
+
+    None
+}
+
+fn idx(x: u32, y: u32) -> usize {
+    (y * 50 + x) as usize
+}
+
+/// Flood fills the room from its exits, treating ramparts & walls as impassable
+fn reachable_from_exits(room: &Room, structures: &[Structure]) -> Vec<bool> {
+    let terrain = room.get_terrain();
+    let mut blocked = vec![false; 2500];
+    for y in 0..50 {
+        for x in 0..50 {
+            blocked[idx(x, y)] = terrain.get(x, y) == Terrain::Wall;
+        }
+    }
+    for s in structures {
+        match s {
+            Structure::Rampart(_) | Structure::Wall(_) => {
+                let pos = s.pos();
+                blocked[idx(pos.x(), pos.y())] = true;
+            },
+            _ => (),
+        }
+    }
+
+    let mut reached = vec![false; 2500];
+    let mut queue = VecDeque::new();
+    for i in 0..50 {
+        for &(x, y) in [(i, 0), (i, 49), (0, i), (49, i)].iter() {
+            if !blocked[idx(x, y)] && !reached[idx(x, y)] {
+                reached[idx(x, y)] = true;
+                queue.push_back((x, y));
+            }
+        }
+    }
+
+    while let Some((x, y)) = queue.pop_front() {
+        for dx in -1i32..=1 {
+            for dy in -1i32..=1 {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                if nx < 0 || ny < 0 || nx > 49 || ny > 49 {
+                    continue;
+                }
+                let i = idx(nx as u32, ny as u32);
+                if !blocked[i] && !reached[i] {
+                    reached[i] = true;
+                    queue.push_back((nx as u32, ny as u32));
+                }
+            }
+        }
+    }
+
+    reached
+}
@@ -0,0 +1,61 @@
+//!
+//! Controller signing
+//!
+//! Our own controllers & the ones we reserve carry the `sign.text` message. The creeps already
+//! working a controller, upgraders, harvesters on their way to upgrade & reservers, step up to it
+//! and sign it whenever there's no sign, someone else's, or an outdated message of ours.
+//!
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{Creep, ReturnCode, StructureController};
+
+use crate::util::config;
+
+
+/// Message controllers are signed with unless configured otherwise
+const DEFAULT_TEXT: &str = "Mined & maintained by its neighbours. Please pass through peacefully.";
+
+/// Longest sign the game keeps, anything past it is cut off
+const MAX_SIGN_LENGTH: usize = 100;
+
+
+/// Message to sign controllers with, cut to what the game keeps so it matches the signs it leaves
+pub fn text() -> String {
+    config::string_or("sign.text", DEFAULT_TEXT).chars().take(MAX_SIGN_LENGTH).collect()
+}
+
+/// Whether the controller is missing our sign, as seen by one of our creeps
+fn needs_sign(creep: &Creep, controller: &StructureController) -> bool {
+    if !config::bool_or("sign.enabled", true) {
+        return false;
+    }
+    match controller.sign() {
+        Some(sign) => sign.username != creep.owner_name() || sign.text != text(),
+        None => true,
+    }
+}
+
+/// Signs the controller when it's missing our sign, walking up to it first.
+/// Returns whether the creep spent its tick on it.
+pub fn sign(creep: &Creep, controller: &StructureController) -> bool {
+    if !needs_sign(creep, controller) {
+        return false;
+    }
+
+    match creep.sign_controller(controller, &text()) {
+        ReturnCode::Ok => {
+            info!("{} signed the controller in {}", creep.name(), controller.room().name());
+            true
+        },
+        ReturnCode::NotInRange => {
+            creep.move_to(controller);
+            true
+        },
+        r => {
+            debug!("{} couldn't sign the controller in {}: {:?}", creep.name(), controller.room().name(), r);
+            false
+        }
+    }
+}
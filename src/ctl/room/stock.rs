@@ -0,0 +1,156 @@
+//!
+//! Storage & terminal stock levels
+//!
+//! Each room aims to hold a target amount of every resource: an energy reserve in storage,
+//! and a working stock of energy, minerals and compounds in the terminal for trading
+//! and sending. Whatever the terminal holds beyond its targets belongs in storage.
+//!
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{ResourceType, Room};
+
+use crate::metrics;
+use crate::util::{self, config};
+
+
+/// Broad kinds of resources, which get different stock targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceClass {
+    Energy,
+    /// base minerals, as mined from a room
+    Mineral,
+    /// anything made in labs
+    Compound,
+    /// power, commodities and everything else
+    Other,
+}
+
+impl ResourceClass {
+    pub fn of(resource: ResourceType) -> ResourceClass {
+        use ResourceType::*;
+        match resource {
+            Energy => ResourceClass::Energy,
+            Hydrogen | Oxygen | Utrium | Lemergium | Keanium | Zynthium | Catalyst => ResourceClass::Mineral,
+            r if r.reaction_time().is_some() => ResourceClass::Compound,
+            _ => ResourceClass::Other,
+        }
+    }
+}
+
+/// How much of a resource a room holds, against what it aims to hold
+#[derive(Debug, Clone, Copy)]
+pub struct StockLevel {
+    pub resource: ResourceType,
+    /// total held in storage & terminal
+    pub amount: u32,
+    pub target: u32,
+}
+
+impl StockLevel {
+    /// Amount held beyond the target
+    pub fn surplus(&self) -> u32 {
+        self.amount.saturating_sub(self.target)
+    }
+
+    /// Amount missing to reach the target
+    pub fn shortfall(&self) -> u32 {
+        self.target.saturating_sub(self.amount)
+    }
+}
+
+
+/// Energy a room keeps in storage before spending it freely
+pub fn energy_reserve() -> u32 {
+    config::u32_or("stock.energy_reserve", 50_000)
+}
+
+/// Amount of a resource the terminal should hold
+pub fn terminal_target(resource: ResourceType) -> u32 {
+    match ResourceClass::of(resource) {
+        ResourceClass::Energy => config::u32_or("stock.terminal_energy", 20_000),
+        ResourceClass::Mineral => config::u32_or("stock.terminal_mineral", 5_000),
+        ResourceClass::Compound => config::u32_or("stock.terminal_compound", 3_000),
+        ResourceClass::Other => 0,
+    }
+}
+
+/// Amount of a resource the room should hold across storage & terminal
+pub fn room_target(resource: ResourceType) -> u32 {
+    match ResourceClass::of(resource) {
+        ResourceClass::Energy => energy_reserve() + terminal_target(resource),
+        ResourceClass::Mineral => config::u32_or("mineral.stock_cap", 100_000),
+        ResourceClass::Compound => config::u32_or("stock.compound_target", 10_000),
+        ResourceClass::Other => 0,
+    }
+}
+
+/// Amount of a resource held across the room's storage & terminal
+pub fn stored(room: &Room, resource: ResourceType) -> u32 {
+    room.storage().map(|s| s.store_of(resource)).unwrap_or(0)
+        + room.terminal().map(|t| t.store_of(resource)).unwrap_or(0)
+}
+
+/// Gets the room's stock of energy, plus anything else it holds
+pub fn levels(room: &Room) -> Vec<StockLevel> {
+    let mut resources = vec![ResourceType::Energy];
+    let held = room.storage().map(|s| s.store_types()).unwrap_or_default().into_iter()
+        .chain(room.terminal().map(|t| t.store_types()).unwrap_or_default());
+    for resource in held {
+        if !resources.contains(&resource) {
+            resources.push(resource);
+        }
+    }
+
+    resources.into_iter()
+        .map(|resource| StockLevel {
+            resource,
+            amount: stored(room, resource),
+            target: room_target(resource),
+        })
+        .collect()
+}
+
+/// Whether upgraders may spend storage energy: only while the reserve is full
+pub fn spend_freely(room: &Room) -> bool {
+    match room.storage() {
+        Some(storage) => storage.store_of(ResourceType::Energy) >= energy_reserve(),
+        None => false,
+    }
+}
+
+/// Number of upgraders the room can keep busy from its storage surplus
+pub fn upgraders_wanted(room: &Room) -> u32 {
+    if !spend_freely(room) {
+        return 0;
+    }
+    let surplus = stored(room, ResourceType::Energy).saturating_sub(room_target(ResourceType::Energy));
+    (1 + surplus / config::u32_or("stock.upgrader_energy", 50_000).max(1))
+        .min(config::u32_or("stock.max_upgraders", 3))
+}
+
+/// Logs the resources the room holds too much or too little of, and counts them in metrics
+pub fn report(room: &Room) {
+    if room.storage().is_none() {
+        return;
+    }
+
+    let mut over = Vec::new();
+    let mut under = Vec::new();
+    for level in levels(room) {
+        if level.surplus() > 0 && level.target > 0 {
+            over.push(format!("{} +{}", util::resource_name(level.resource), level.surplus()));
+        } else if level.shortfall() > 0 {
+            under.push(format!("{} -{}", util::resource_name(level.resource), level.shortfall()));
+        }
+    }
+
+    if !over.is_empty() {
+        info!("room {} over stock targets: {}", room.name(), over.join(", "));
+    }
+    if !under.is_empty() {
+        info!("room {} under stock targets: {}", room.name(), under.join(", "));
+    }
+    metrics::inc_stock_off_target(&room.name().to_string(), over.len() as u32, under.len() as u32);
+}
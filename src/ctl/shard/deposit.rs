@@ -0,0 +1,244 @@
+//!
+//! Deposit harvesting in highway rooms
+//!
+//! Deposits turn up in the intel gathered by observers & scouts. Every harvest makes a deposit's
+//! cooldown longer, so one is only worth working while its cooldown still leaves a harvester
+//! enough harvests after its walk over. Rooms with a factory send dedicated harvesters & haulers
+//! to the best one in reach, and the haulers bring the resources home to storage, where the
+//! factory picks them up for its commodities.
+//!
+//! Operations are kept in `Memory.deposits.<room>`, and called off once the cooldown grows too
+//! long or the deposit decays.
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Position, ResourceType, Room, RoomName};
+use screeps::constants::*;
+use screeps::memory;
+
+use crate::ctl::creep::types::{CreepInfo, DepositHarvester, DepositHauler};
+use crate::factory;
+use crate::util::{self, config};
+
+use super::intel;
+
+
+/// Fewest harvests a harvester must get in after walking over for a deposit to be worth it
+const MIN_HARVESTS: u32 = 20;
+
+
+/// An operation harvesting a deposit
+#[derive(Debug, Clone)]
+pub struct DepositOp {
+    /// room the deposit is in
+    pub room: RoomName,
+    pub pos: Position,
+    pub resource: ResourceType,
+    /// owned room sending creeps to the deposit
+    pub home: RoomName,
+    /// cooldown after the last harvest
+    pub cooldown: u32,
+    /// tick the deposit disappears at, unless it's harvested
+    pub decays_at: u32,
+    pub harvesters: u32,
+    pub haulers: u32,
+}
+
+/// A creep a home room should spawn for a deposit operation
+#[derive(Debug, Clone)]
+pub enum DepositSpawn {
+    Harvester(DepositOp),
+    Hauler(DepositOp),
+}
+
+
+impl DepositOp {
+    /// Loads the operation on a room's deposit, if there is one
+    pub fn load(room: RoomName) -> Option<DepositOp> {
+        let mem = memory::root().path_dict(&format!("deposits.{}", room)).ok()??;
+        let get = |key: &str| mem.i32(key).ok().flatten().map(|v| v as u32);
+        Some(DepositOp {
+            room,
+            pos: Position::from_packed(mem.i32("pos").ok()??),
+            resource: ResourceType::from_str(&mem.string("resource").ok()??).ok()?,
+            home: RoomName::from_str(&mem.string("home").ok()??).ok()?,
+            cooldown: get("cooldown")?,
+            decays_at: get("decays")?,
+            harvesters: get("harvesters")?,
+            haulers: get("haulers")?,
+        })
+    }
+
+    fn save(&self) {
+        let path = format!("deposits.{}", self.room);
+        let mem = memory::root();
+        mem.path_set(&format!("{}.pos", path), self.pos.packed_repr());
+        mem.path_set(&format!("{}.resource", path), util::resource_name(self.resource));
+        mem.path_set(&format!("{}.home", path), self.home.to_string());
+        mem.path_set(&format!("{}.cooldown", path), self.cooldown);
+        mem.path_set(&format!("{}.decays", path), self.decays_at);
+        mem.path_set(&format!("{}.harvesters", path), self.harvesters);
+        mem.path_set(&format!("{}.haulers", path), self.haulers);
+    }
+
+    /// Ticks the creeps spend getting from the home room to the deposit
+    pub fn travel_ticks(&self) -> u32 {
+        util::travel_ticks(self.home, self.room)
+    }
+}
+
+
+/// Whether a deposit with the given cooldown is worth walking the given ticks to
+pub fn worth_harvesting(cooldown: u32, travel: u32) -> bool {
+    let working = CREEP_LIFE_TIME.saturating_sub(travel);
+    cooldown <= config::u32_or("deposit.max_cooldown", 100) && working / (cooldown + 1) >= MIN_HARVESTS
+}
+
+/// Haulers needed to keep up with harvesters with the given work parts each, bringing the
+/// resources home over the given trip with the given carry parts each
+pub fn haulers_needed(cooldown: u32, work_parts: u32, harvesters: u32, travel: u32, carry_parts: u32) -> u32 {
+    let capacity = carry_parts * CARRY_CAPACITY;
+    if capacity == 0 {
+        return 0;
+    }
+    let per_trip = work_parts * harvesters * travel * 2 / (cooldown + 1);
+    per_trip.div_ceil(capacity).clamp(1, config::u32_or("deposit.max_haulers", 3))
+}
+
+/// Gets all running operations
+pub fn all_ops() -> Vec<DepositOp> {
+    match memory::root().dict("deposits") {
+        Ok(Some(mem)) => mem.keys().iter()
+            .filter_map(|name| RoomName::from_str(name).ok())
+            .filter_map(DepositOp::load)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Calls off an operation; its creeps head home when they notice it's gone
+pub fn cancel(room: RoomName, reason: &str) {
+    info!("done with deposit in {}: {}", room, reason);
+    memory::root().path_del(&format!("deposits.{}", room));
+}
+
+/// Work parts a home room's deposit harvesters get, matched with carry & move parts
+fn harvester_parts(home: &Room) -> u32 {
+    body_units(home.energy_capacity_available(), DepositHarvester::cost(), DepositHarvester::parts().len() as u32)
+}
+
+/// Carry parts a home room's deposit haulers get, matched with move parts
+fn hauler_parts(home: &Room) -> u32 {
+    body_units(home.energy_capacity_available(), DepositHauler::cost(), DepositHauler::parts().len() as u32)
+}
+
+/// Units of a body a room's full energy affords, within the creep size limit
+fn body_units(energy: u32, unit_cost: u32, unit_len: u32) -> u32 {
+    (energy / unit_cost).min(MAX_CREEP_SIZE / unit_len)
+}
+
+
+/// Looks for a deposit worth harvesting, when there's room for another operation
+pub fn plan(username: &str) {
+    let ops = all_ops();
+    if !config::bool_or("deposit.enabled", true) || ops.len() as u32 >= config::u32_or("deposit.max_ops", 2) {
+        return;
+    }
+
+    // only rooms with a factory have a use for what deposits give
+    let homes: Vec<Room> = util::owned_rooms().into_iter()
+        .filter(|r| factory::factory(r).is_some())
+        .collect();
+    if homes.is_empty() {
+        return;
+    }
+
+    let time = screeps::game::time();
+    let max_distance = config::u32_or("deposit.max_distance", 4);
+
+    let best = intel::all_intel().into_iter()
+        .filter(|i| !ops.iter().any(|o| o.room == i.name))
+        .filter(|i| !intel::should_avoid(i.name, username))
+        .flat_map(|i| i.deposits.into_iter())
+        .filter(|d| d.decays_at > time)
+        .filter_map(|deposit| {
+            let room = deposit.pos.room_name();
+            let (home, distance) = homes.iter()
+                .map(|h| (h, screeps::game::map::get_room_linear_distance(h.name(), room, false)))
+                .min_by_key(|(_, d)| *d)?;
+            let travel = util::travel_ticks(home.name(), room);
+            if distance > max_distance || !worth_harvesting(deposit.cooldown, travel) {
+                return None;
+            }
+
+            let harvesters = util::open_tiles(deposit.pos).min(config::u32_or("deposit.harvesters", 2));
+            Some(DepositOp {
+                room,
+                pos: deposit.pos,
+                resource: deposit.resource,
+                home: home.name(),
+                cooldown: deposit.cooldown,
+                decays_at: deposit.decays_at,
+                harvesters,
+                haulers: haulers_needed(deposit.cooldown, harvester_parts(home), harvesters, travel, hauler_parts(home)),
+            })
+        })
+        .min_by_key(|op| (op.cooldown, op.travel_ticks()));
+
+    if let Some(op) = best {
+        info!("harvesting {} deposit in {} from {}: {} harvesters, {} haulers",
+            util::resource_name(op.resource), op.room, op.home, op.harvesters, op.haulers);
+        op.save();
+    }
+}
+
+/// Keeps the operations up to date with what the creeps see,
+/// and calls them off when the deposit is gone or its cooldown has grown too long
+pub fn update() {
+    let time = screeps::game::time();
+    for mut op in all_ops() {
+        if let Some(room) = screeps::game::rooms::get(op.room) {
+            let deposit = room.find(find::DEPOSITS).into_iter()
+                .find(|d| d.pos() == op.pos);
+            match deposit {
+                Some(deposit) => {
+                    op.cooldown = deposit.last_cooldown();
+                    op.decays_at = time + deposit.ticks_to_decay();
+                    op.save();
+                },
+                None => {
+                    cancel(op.room, "the deposit is gone");
+                    continue;
+                },
+            }
+        } else if time > op.decays_at {
+            cancel(op.room, "the deposit decayed");
+            continue;
+        }
+
+        if !worth_harvesting(op.cooldown, op.travel_ticks()) {
+            cancel(op.room, &format!("cooldown is up to {}", op.cooldown));
+        }
+    }
+}
+
+/// Picks the next creep a home room should spawn for its deposit operations, if any
+pub fn next_spawn(home: &Room) -> Option<DepositSpawn> {
+    all_ops().into_iter()
+        .filter(|o| o.home == home.name())
+        .find_map(|op| {
+            let key = op.room.to_string();
+            if util::count_creeps(DepositHarvester::role(), "op", &key) < op.harvesters {
+                Some(DepositSpawn::Harvester(op))
+            } else if util::count_creeps(DepositHauler::role(), "op", &key) < op.haulers {
+                Some(DepositSpawn::Hauler(op))
+            } else {
+                None
+            }
+        })
+}
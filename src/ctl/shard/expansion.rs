@@ -0,0 +1,322 @@
+//!
+//! Claiming & bootstrapping new rooms
+//!
+//! One expansion runs at a time, tracked in `Memory.expansion` along with the owned room
+//! supporting it. Candidates are picked from room intel, a claimer takes the controller,
+//! then pioneers from the parent room build up the new room until it can stand on its own.
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Position, Room, RoomName, StructureType};
+use screeps::constants::*;
+use screeps::memory;
+
+use crate::ctl::creep::types::{Claimer, CreepInfo, Pioneer};
+use crate::remote;
+use crate::util::{self, config};
+
+use super::intel::{self, RoomIntel};
+
+
+/// Width & height of a room, in tiles
+const ROOM_SIZE: u32 = 50;
+
+
+/// Where the expansion is at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpansionStage {
+    /// waiting on a claimer to take the controller
+    Claiming,
+    /// building up the claimed room with workers from the parent room
+    Bootstrapping,
+}
+
+impl ExpansionStage {
+    fn as_str(self) -> &'static str {
+        match self {
+            ExpansionStage::Claiming => "claiming",
+            ExpansionStage::Bootstrapping => "bootstrapping",
+        }
+    }
+
+    fn from_str(s: &str) -> ExpansionStage {
+        match s {
+            "bootstrapping" => ExpansionStage::Bootstrapping,
+            _ => ExpansionStage::Claiming,
+        }
+    }
+}
+
+/// The room currently being expanded into
+#[derive(Debug, Clone)]
+pub struct Expansion {
+    pub target: RoomName,
+    /// owned room sending creeps to the target
+    pub parent: RoomName,
+    pub stage: ExpansionStage,
+    /// tick the expansion was planned
+    pub started: u32,
+}
+
+/// A creep a parent room should spawn for an expansion
+#[derive(Debug, Clone, Copy)]
+pub enum ExpansionSpawn {
+    Claimer(RoomName),
+    Pioneer(RoomName),
+}
+
+
+impl Expansion {
+    /// Loads the running expansion from memory, if there is one
+    pub fn load() -> Option<Expansion> {
+        let mem = memory::root().dict("expansion").ok()??;
+        Some(Expansion {
+            target: RoomName::from_str(&mem.string("target").ok()??).ok()?,
+            parent: RoomName::from_str(&mem.string("parent").ok()??).ok()?,
+            stage: ExpansionStage::from_str(&mem.string("stage").ok()?.unwrap_or_default()),
+            started: mem.i32("started").ok()?? as u32,
+        })
+    }
+
+    fn save(&self) {
+        let mem = memory::root();
+        mem.path_set("expansion.target", self.target.to_string());
+        mem.path_set("expansion.parent", self.parent.to_string());
+        mem.path_set("expansion.stage", self.stage.as_str());
+        mem.path_set("expansion.started", self.started);
+    }
+
+    fn set_stage(&mut self, stage: ExpansionStage) {
+        if self.stage != stage {
+            info!("expansion into {} (parent {}): {:?} -> {:?}", self.target, self.parent, self.stage, stage);
+        }
+        self.stage = stage;
+        memory::root().path_set("expansion.stage", stage.as_str());
+    }
+}
+
+
+/// Whether the GCL leaves room to claim another room, and expansion is turned on
+pub fn can_expand() -> bool {
+    config::bool_or("expansion.enabled", true)
+        && (util::owned_rooms().len() as u32) < screeps::game::gcl::level()
+}
+
+/// Gives up on the running expansion, marking the target so it isn't picked again
+pub fn abandon(reason: &str) {
+    if let Some(expansion) = Expansion::load() {
+        warn!("abandoning expansion into {}: {}", expansion.target, reason);
+        memory::root().path_set(&format!("intel.{}.rejected", expansion.target), true);
+        memory::root().del("expansion");
+    }
+}
+
+
+/// Scores a room as an expansion target, or `None` if it can't be claimed.
+/// More sources, a mineral we don't have yet, open terrain and being close to the parent
+/// all count for a room, while neighbours held by other players count against it.
+pub fn score(
+    room: &RoomIntel,
+    distance: u32,
+    hostile_neighbours: u32,
+    owned_minerals: &[ResourceType],
+    username: &str,
+) -> Option<u32> {
+    if !room.has_controller || room.owner.is_some() || room.is_hostile(username) || room.sources.is_empty() {
+        return None;
+    }
+
+    let mineral = match room.mineral {
+        Some(m) if !owned_minerals.contains(&m) => 50,
+        Some(_) => 10,
+        None => 0,
+    };
+    let score = (room.sources.len() as u32).min(2) * 100 + room.openness + mineral;
+    let penalty = distance * 20 + hostile_neighbours * 75;
+    Some(score.saturating_sub(penalty))
+}
+
+/// Picks a room to expand into when none is running and the GCL allows it
+pub fn plan(username: &str) {
+    if Expansion::load().is_some() || !can_expand() {
+        return;
+    }
+
+    let min_rcl = config::u32_or("expansion.min_parent_rcl", 4);
+    let parents: Vec<Room> = util::owned_rooms().into_iter()
+        .filter(|r| r.controller().map(|c| c.level()).unwrap_or(0) >= min_rcl)
+        .filter(|r| r.energy_capacity_available() >= Claimer::cost())
+        .collect();
+    if parents.is_empty() {
+        return;
+    }
+
+    let all = intel::all_intel();
+    let owned_minerals: Vec<ResourceType> = all.iter()
+        .filter(|i| i.owner.as_deref() == Some(username))
+        .filter_map(|i| i.mineral)
+        .collect();
+    let remotes: Vec<RoomName> = remote::all_remotes().iter().map(|r| r.name).collect();
+    let max_distance = config::u32_or("expansion.max_distance", 4);
+    let max_age = config::u32_or("expansion.max_intel_age", 20_000);
+
+    let best = all.iter()
+        .filter(|i| i.age() <= max_age && !remotes.contains(&i.name))
+        .filter(|i| !util::is_highway(i.name) && !util::is_source_keeper(i.name))
+        .filter(|i| !memory::root().path_bool(&format!("intel.{}.rejected", i.name)))
+        .filter_map(|i| {
+            let (parent, distance) = parents.iter()
+                .map(|p| (p.name(), screeps::game::map::get_room_linear_distance(p.name(), i.name, false)))
+                .min_by_key(|(_, d)| *d)?;
+            if distance > max_distance {
+                return None;
+            }
+
+            let hostile_neighbours = screeps::game::map::describe_exits(i.name).values()
+                .filter_map(|name| RoomName::from_str(name).ok())
+                .filter_map(RoomIntel::load)
+                .filter(|n| n.is_hostile(username))
+                .count() as u32;
+            score(i, distance, hostile_neighbours, &owned_minerals, username)
+                .map(|s| (s, i.name, parent))
+        })
+        .max_by_key(|(s, _, _)| *s);
+
+    if let Some((score, target, parent)) = best {
+        info!("expanding into {} from {} (score {})", target, parent, score);
+        Expansion {
+            target,
+            parent,
+            stage: ExpansionStage::Claiming,
+            started: screeps::game::time(),
+        }.save();
+    }
+}
+
+/// Moves the running expansion along: notices the claim, lays down the first spawn,
+/// and finishes once the new room has an economy of its own
+pub fn update() {
+    let mut expansion = match Expansion::load() {
+        Some(e) => e,
+        None => return,
+    };
+
+    let room = screeps::game::rooms::get(expansion.target);
+    let controller = room.as_ref().and_then(|r| r.controller());
+    match expansion.stage {
+        ExpansionStage::Claiming => {
+            if matches!(&controller, Some(c) if c.my()) {
+                expansion.set_stage(ExpansionStage::Bootstrapping);
+            } else if screeps::game::time() - expansion.started > config::u32_or("expansion.claim_timeout", 5_000) {
+                abandon("controller wasn't claimed in time");
+            }
+        },
+        ExpansionStage::Bootstrapping => {
+            let (room, controller) = match (room, controller) {
+                (Some(r), Some(c)) => (r, c),
+                _ => return,
+            };
+            if !controller.my() {
+                abandon("lost the controller");
+                return;
+            }
+
+            let has_spawn = !room.find(find::MY_SPAWNS).is_empty();
+            if has_spawn && controller.level() >= config::u32_or("expansion.bootstrap_rcl", 3) {
+                info!("expansion into {} is self-sufficient", expansion.target);
+                memory::root().del("expansion");
+            } else if !has_spawn {
+                place_spawn(&room);
+            }
+        },
+    }
+}
+
+/// Picks the next creep the parent room should spawn for the expansion, if any
+pub fn next_spawn(parent: &Room) -> Option<ExpansionSpawn> {
+    let expansion = Expansion::load()?;
+    if expansion.parent != parent.name() {
+        return None;
+    }
+
+    let target = expansion.target.to_string();
+    match expansion.stage {
+        ExpansionStage::Claiming => {
+            if util::count_creeps(Claimer::role(), "target", &target) == 0 {
+                Some(ExpansionSpawn::Claimer(expansion.target))
+            } else {
+                None
+            }
+        },
+        ExpansionStage::Bootstrapping => {
+            let wanted = config::u32_or("expansion.pioneers", 4);
+            if util::count_creeps(Pioneer::role(), "target", &target) < wanted {
+                Some(ExpansionSpawn::Pioneer(expansion.target))
+            } else {
+                None
+            }
+        },
+    }
+}
+
+
+/// Places the construction site for a claimed room's first spawn, unless there already is one
+fn place_spawn(room: &Room) {
+    let placed = room.find(find::MY_CONSTRUCTION_SITES).iter()
+        .any(|s| s.structure_type() == StructureType::Spawn);
+    if placed {
+        return;
+    }
+
+    match spawn_site(room) {
+        Some(pos) => {
+            info!("placing first spawn of room {} at {}", room.name(), pos);
+            room.create_construction_site(&pos, StructureType::Spawn);
+        },
+        None => warn!("no space for a spawn in room {}", room.name()),
+    }
+}
+
+/// Finds a spot for a spawn between the sources & controller, with open ground all around it
+fn spawn_site(room: &Room) -> Option<Position> {
+    let mut points: Vec<Position> = room.find(find::SOURCES).iter().map(|s| s.pos()).collect();
+    points.extend(room.controller().map(|c| c.pos()));
+    if points.is_empty() {
+        return None;
+    }
+
+    let n = points.len() as u32;
+    let cx = points.iter().map(|p| p.x()).sum::<u32>() / n;
+    let cy = points.iter().map(|p| p.y()).sum::<u32>() / n;
+
+    let terrain = room.get_terrain().get_raw_buffer();
+    let open = |x: u32, y: u32| terrain[(y * ROOM_SIZE + x) as usize] & 1 == 0;
+
+    // search outwards from the middle, keeping clear of the exits
+    for range in 0..15i32 {
+        for dx in -range..=range {
+            for dy in -range..=range {
+                if dx.abs() != range && dy.abs() != range {
+                    continue;
+                }
+                let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                if !(3..ROOM_SIZE as i32 - 3).contains(&x) || !(3..ROOM_SIZE as i32 - 3).contains(&y) {
+                    continue;
+                }
+                let (x, y) = (x as u32, y as u32);
+                let clear = (x - 1..=x + 1).all(|ax| (y - 1..=y + 1).all(|ay| open(ax, ay)));
+                let pos = Position::new(x, y, room.name());
+                if clear && !points.iter().any(|p| p.get_range_to(&pos) < 2) {
+                    return Some(pos);
+                }
+            }
+        }
+    }
+
+    None
+}
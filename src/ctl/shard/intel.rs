@@ -0,0 +1,317 @@
+//!
+//! What we know about rooms across the shard
+//!
+//! Rooms are summarized in `Memory.intel.<name>` whenever we have vision of them,
+//! so decisions about rooms we can't currently see can still be made. Scouts keep
+//! the rooms around our own from going stale.
+//!
+
+use std::collections::{HashSet, VecDeque};
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Attackable, Position, ResourceType, Room, RoomName, Structure, StructureType};
+use screeps::memory;
+
+use crate::ctl::creep::types::{CreepInfo, Scout};
+use crate::util::{self, config};
+
+
+/// Ticks between refreshes of a visible room's intel
+const REFRESH_TICKS: u32 = 50;
+
+
+/// A power bank, as of the last time its room was seen
+#[derive(Debug, Clone)]
+pub struct PowerBankIntel {
+    pub pos: Position,
+    pub power: u32,
+    pub hits: u32,
+    /// tick the bank disappears at
+    pub decays_at: u32,
+}
+
+/// A deposit, as of the last time its room was seen
+#[derive(Debug, Clone)]
+pub struct DepositIntel {
+    pub pos: Position,
+    pub resource: ResourceType,
+    /// cooldown after the last harvest, which grows as the deposit is worked
+    pub cooldown: u32,
+    /// tick the deposit disappears at, unless it's harvested
+    pub decays_at: u32,
+}
+
+/// A summary of a room, as of the last time we saw it
+#[derive(Debug, Clone)]
+pub struct RoomIntel {
+    pub name: RoomName,
+    /// tick the room was last seen
+    pub last_seen: u32,
+    pub has_controller: bool,
+    /// player owning the controller
+    pub owner: Option<String>,
+    /// controller level, 0 when unowned
+    pub level: u32,
+    /// player reserving the controller
+    pub reserved_by: Option<String>,
+    /// ticks left on the reservation when last seen
+    pub reservation: u32,
+    /// (id, position) of each source
+    pub sources: Vec<(String, Position)>,
+    pub mineral: Option<ResourceType>,
+    pub mineral_pos: Option<Position>,
+    /// number of structures belonging to other players
+    pub hostile_structures: u32,
+    /// positions of towers belonging to other players
+    pub towers: Vec<Position>,
+    pub portals: Vec<Position>,
+    pub power_banks: Vec<PowerBankIntel>,
+    pub deposits: Vec<DepositIntel>,
+    /// percentage of the room's tiles that aren't walls
+    pub openness: u32,
+}
+
+impl RoomIntel {
+    /// Loads a room's intel from memory
+    pub fn load(name: RoomName) -> Option<RoomIntel> {
+        let mem = memory::root().path_dict(&format!("intel.{}", name)).ok()??;
+        let positions = |key: &str| -> Vec<Position> {
+            mem.arr::<i32>(key).ok().flatten().unwrap_or_default()
+                .into_iter()
+                .map(Position::from_packed)
+                .collect()
+        };
+
+        let sources = match mem.dict("sources").ok().flatten() {
+            Some(sources) => sources.keys().into_iter()
+                .filter_map(|id| {
+                    let pos = Position::from_packed(sources.i32(&id).ok()??);
+                    Some((id, pos))
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let power_banks = match mem.dict("power_banks").ok().flatten() {
+            Some(banks) => banks.keys().into_iter()
+                .filter_map(|key| {
+                    let bank = banks.dict(&key).ok()??;
+                    Some(PowerBankIntel {
+                        pos: Position::from_packed(key.parse().ok()?),
+                        power: bank.i32("power").ok()?? as u32,
+                        hits: bank.i32("hits").ok()?? as u32,
+                        decays_at: bank.i32("decays").ok()?? as u32,
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let deposits = match mem.dict("deposits").ok().flatten() {
+            Some(deposits) => deposits.keys().into_iter()
+                .filter_map(|key| {
+                    let deposit = deposits.dict(&key).ok()??;
+                    Some(DepositIntel {
+                        pos: Position::from_packed(key.parse().ok()?),
+                        resource: ResourceType::from_str(&deposit.string("type").ok()??).ok()?,
+                        cooldown: deposit.i32("cooldown").ok()?? as u32,
+                        decays_at: deposit.i32("decays").ok()?? as u32,
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Some(RoomIntel {
+            name,
+            last_seen: mem.i32("seen").ok()?? as u32,
+            has_controller: mem.bool("controller"),
+            owner: mem.string("owner").ok().flatten(),
+            level: mem.i32("level").ok().flatten().unwrap_or(0) as u32,
+            reserved_by: mem.string("reserved").ok().flatten(),
+            reservation: mem.i32("reservation").ok().flatten().unwrap_or(0) as u32,
+            sources,
+            mineral: mem.string("mineral").ok().flatten()
+                .and_then(|m| ResourceType::from_str(&m).ok()),
+            mineral_pos: mem.i32("mineral_pos").ok().flatten().map(Position::from_packed),
+            hostile_structures: mem.i32("hostile_structures").ok().flatten().unwrap_or(0) as u32,
+            towers: positions("towers"),
+            portals: positions("portals"),
+            power_banks,
+            deposits,
+            openness: mem.i32("open").ok().flatten().unwrap_or(0) as u32,
+        })
+    }
+
+    /// Ticks since the room was last seen
+    pub fn age(&self) -> u32 {
+        screeps::game::time().saturating_sub(self.last_seen)
+    }
+
+    /// Whether the room is owned or reserved by another player
+    pub fn is_hostile(&self, username: &str) -> bool {
+        matches!(&self.owner, Some(o) if o != username)
+            || matches!(&self.reserved_by, Some(r) if r != username)
+    }
+
+    /// Whether creeps passing through should route around the room
+    pub fn should_avoid(&self, username: &str) -> bool {
+        matches!(&self.owner, Some(o) if o != username) && !self.towers.is_empty()
+    }
+}
+
+
+/// Records what can be seen of a visible room, unless it was recorded recently
+pub fn record(room: &Room) {
+    let time = screeps::game::time();
+    let seen = memory::root().path_i32(&format!("intel.{}.seen", room.name())).ok().flatten();
+    if matches!(seen, Some(s) if time - (s as u32) < REFRESH_TICKS) {
+        return;
+    }
+    record_now(room);
+}
+
+/// Records what can be seen of a visible room
+pub fn record_now(room: &Room) {
+    let path = format!("intel.{}", room.name());
+    let time = screeps::game::time();
+
+    trace!("recording intel for room {}", room.name());
+    let mem = memory::root();
+    let set = |key: &str, value: Option<String>| match value {
+        Some(v) => mem.path_set(&format!("{}.{}", path, key), v),
+        None => mem.path_del(&format!("{}.{}", path, key)),
+    };
+
+    let controller = room.controller();
+    let reservation = controller.as_ref().and_then(util::reservation);
+    mem.path_set(&format!("{}.seen", path), time);
+    mem.path_set(&format!("{}.controller", path), controller.is_some());
+    set("owner", controller.as_ref().and_then(|c| c.owner_name()));
+    mem.path_set(&format!("{}.level", path), controller.as_ref().map(|c| c.level()).unwrap_or(0));
+    set("reserved", reservation.as_ref().map(|(user, _)| user.clone()));
+    mem.path_set(&format!("{}.reservation", path), reservation.map(|(_, ticks)| ticks).unwrap_or(0));
+
+    for source in room.find(find::SOURCES) {
+        mem.path_set(&format!("{}.sources.{}", path, source.id()), source.pos().packed_repr());
+    }
+    if let Some(mineral) = room.find(find::MINERALS).first() {
+        mem.path_set(&format!("{}.mineral", path), util::resource_name(mineral.mineral_type()));
+        mem.path_set(&format!("{}.mineral_pos", path), mineral.pos().packed_repr());
+    }
+
+    let hostile = room.find(find::HOSTILE_STRUCTURES);
+    let towers: Vec<i32> = hostile.iter()
+        .filter(|s| s.structure_type() == StructureType::Tower)
+        .map(|s| s.pos().packed_repr())
+        .collect();
+    let portals: Vec<i32> = room.find(find::STRUCTURES).iter()
+        .filter(|s| s.structure_type() == StructureType::Portal)
+        .map(|s| s.pos().packed_repr())
+        .collect();
+    mem.path_set(&format!("{}.hostile_structures", path), hostile.len() as u32);
+    mem.path_set(&format!("{}.towers", path), towers);
+    mem.path_set(&format!("{}.portals", path), portals);
+
+    // banks & deposits come and go, only keep the ones still there
+    mem.path_del(&format!("{}.power_banks", path));
+    for s in room.find(find::STRUCTURES) {
+        if let Structure::PowerBank(bank) = s {
+            let key = format!("{}.power_banks.{}", path, bank.pos().packed_repr());
+            mem.path_set(&format!("{}.power", key), bank.power());
+            mem.path_set(&format!("{}.hits", key), bank.hits());
+            mem.path_set(&format!("{}.decays", key), time + bank.ticks_to_decay());
+        }
+    }
+    mem.path_del(&format!("{}.deposits", path));
+    for deposit in room.find(find::DEPOSITS) {
+        let key = format!("{}.deposits.{}", path, deposit.pos().packed_repr());
+        mem.path_set(&format!("{}.type", key), util::resource_name(deposit.deposit_type()));
+        mem.path_set(&format!("{}.cooldown", key), deposit.last_cooldown());
+        mem.path_set(&format!("{}.decays", key), time + deposit.ticks_to_decay());
+    }
+
+    // terrain doesn't change, only work it out once
+    if mem.path_i32(&format!("{}.open", path)).ok().flatten().is_none() {
+        let terrain = room.get_terrain().get_raw_buffer();
+        let open = terrain.iter().filter(|t| **t & 1 == 0).count() as u32;
+        mem.path_set(&format!("{}.open", path), open * 100 / terrain.len() as u32);
+    }
+}
+
+/// Gets all rooms we have intel on
+pub fn all_intel() -> Vec<RoomIntel> {
+    match memory::root().dict("intel") {
+        Ok(Some(intel)) => intel.keys().into_iter()
+            .filter_map(|name| RoomName::from_str(&name).ok())
+            .filter_map(RoomIntel::load)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether creeps should route around the room, going by what we last saw of it
+pub fn should_avoid(room_name: RoomName, username: &str) -> bool {
+    matches!(RoomIntel::load(room_name), Some(i) if i.should_avoid(username))
+}
+
+
+/// Gets the rooms within the given number of room exits of a room, not including the room itself
+pub fn rooms_near(origin: RoomName, range: u32) -> Vec<RoomName> {
+    let mut seen: HashSet<RoomName> = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(origin);
+    queue.push_back((origin, 0));
+
+    let mut rooms = Vec::new();
+    while let Some((room, depth)) = queue.pop_front() {
+        if depth >= range {
+            continue;
+        }
+        for name in screeps::game::map::describe_exits(room).values() {
+            if let Ok(next) = RoomName::from_str(name) {
+                if seen.insert(next) {
+                    rooms.push(next);
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+    }
+
+    rooms
+}
+
+/// Gets the rooms around an owned room with intel missing or old enough to be re-scouted
+pub fn stale_rooms(home: RoomName) -> Vec<RoomName> {
+    let range = config::u32_or("scout.range", 3);
+    let max_age = config::u32_or("scout.rescout_ticks", 5_000);
+    let time = screeps::game::time();
+
+    rooms_near(home, range).into_iter()
+        .filter(|name| screeps::game::rooms::get(*name).is_none())
+        .filter(|name| {
+            let skip_until = memory::root().path_i32(&format!("intel.{}.skip_until", name)).ok().flatten();
+            !matches!(skip_until, Some(t) if time < t as u32)
+        })
+        .filter(|name| match RoomIntel::load(*name) {
+            Some(i) => i.age() > max_age,
+            None => true,
+        })
+        .collect()
+}
+
+/// Stops scouts from trying a room for a while, e.g. one they couldn't reach
+pub fn skip(room_name: RoomName) {
+    let until = screeps::game::time() + config::u32_or("scout.rescout_ticks", 5_000);
+    memory::root().path_set(&format!("intel.{}.skip_until", room_name), until);
+}
+
+/// Whether an owned room should send out a scout: it has none, and rooms around it are stale
+pub fn needs_scout(home: RoomName) -> bool {
+    util::count_creeps(Scout::role(), "home", &home.to_string()) == 0
+        && !stale_rooms(home).is_empty()
+}
@@ -0,0 +1,731 @@
+//!
+//! Trading on the market
+//!
+//! Rooms sell what they hold well beyond their stock targets, and buy the lab inputs none of
+//! our other rooms can send them. Prices are judged against a rolling history of each
+//! resource's market price, and the energy a terminal pays to send counts against every deal.
+//! Where nobody trades at a fair price we list orders of our own instead, all within a budget
+//! of credits per period.
+//!
+//! The decisions are plain functions over an order book, so they can be checked against
+//! recorded ones; `run` reads the live market and carries them out.
+//!
+
+use std::collections::HashMap;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{ResourceType, ReturnCode, Room, RoomName};
+use screeps::game::market::{self, OrderType};
+use screeps::memory;
+
+use crate::metrics;
+use crate::stock::{self, ResourceClass};
+use crate::util::{self, config, MarketOrder};
+
+use super::terminal::{self, Priority};
+
+
+/// Fee the market charges for listing an order, as a fraction of its total price
+const ORDER_FEE: f64 = 0.05;
+
+
+/// Limits the market decisions work within
+#[derive(Debug, Clone, Copy)]
+pub struct Policy {
+    /// lowest price we sell at, as a fraction of the fair price
+    pub min_sell_ratio: f64,
+    /// highest price we buy at, as a fraction of the fair price
+    pub max_buy_ratio: f64,
+    /// how far our orders' prices may drift from the fair price before they're replaced
+    pub max_drift: f64,
+    /// ticks our orders stay listed before they're replaced
+    pub max_order_age: u32,
+    /// smallest amount worth a trade
+    pub min_trade: u32,
+    /// largest amount traded at once
+    pub max_trade: u32,
+}
+
+impl Policy {
+    pub fn from_config() -> Policy {
+        Policy {
+            min_sell_ratio: config::u32_or("market.min_sell_percent", 90) as f64 / 100.0,
+            max_buy_ratio: config::u32_or("market.max_buy_percent", 110) as f64 / 100.0,
+            max_drift: config::u32_or("market.max_drift_percent", 20) as f64 / 100.0,
+            max_order_age: config::u32_or("market.order_ticks", 10_000),
+            min_trade: config::u32_or("market.min_trade", 1_000),
+            max_trade: config::u32_or("market.max_trade", 10_000),
+        }
+    }
+}
+
+/// Recent market prices of a resource, oldest first
+#[derive(Debug, Clone)]
+pub struct PriceHistory {
+    pub resource: ResourceType,
+    pub samples: Vec<f64>,
+}
+
+impl PriceHistory {
+    /// Loads the resource's history from `Memory.market.prices`
+    pub fn load(resource: ResourceType) -> PriceHistory {
+        let path = format!("market.prices.{}", util::resource_name(resource));
+        PriceHistory {
+            resource,
+            samples: memory::root().path_arr::<f64>(&path).ok().flatten().unwrap_or_default(),
+        }
+    }
+
+    pub fn save(&self) {
+        let path = format!("market.prices.{}", util::resource_name(self.resource));
+        memory::root().path_set(&path, self.samples.clone());
+    }
+
+    /// Adds a price, dropping the oldest ones beyond the history's size
+    pub fn record(&mut self, price: f64, size: usize) {
+        self.samples.push(price);
+        if self.samples.len() > size {
+            self.samples.drain(..self.samples.len() - size);
+        }
+    }
+
+    /// Average price, once there are enough samples to trust it
+    pub fn fair_price(&self, min_samples: usize) -> Option<f64> {
+        if self.samples.is_empty() || self.samples.len() < min_samples {
+            return None;
+        }
+        Some(self.samples.iter().sum::<f64>() / self.samples.len() as f64)
+    }
+}
+
+/// A trade against someone else's order
+#[derive(Debug, Clone)]
+pub struct Deal {
+    pub order_id: String,
+    /// our room, whose terminal trades
+    pub room: RoomName,
+    pub resource: ResourceType,
+    pub amount: u32,
+    pub price: f64,
+    /// energy our terminal pays for the transfer
+    pub energy: u32,
+    /// credits the deal makes, counting the energy at its market price; negative for purchases
+    pub value: f64,
+}
+
+/// An amount of a resource one of our rooms wants to sell or buy
+#[derive(Debug, Clone, Copy)]
+pub struct TradeRequest {
+    pub room: RoomName,
+    pub resource: ResourceType,
+    pub amount: u32,
+    /// energy in the room's terminal, which pays for transfers
+    pub terminal_energy: u32,
+}
+
+/// An order we'd like listed
+#[derive(Debug, Clone, Copy)]
+pub struct WantedOrder {
+    pub order_type: OrderType,
+    pub room: RoomName,
+    pub resource: ResourceType,
+    pub amount: u32,
+}
+
+/// A change to our own orders
+#[derive(Debug, Clone)]
+pub enum OrderAction {
+    Create {
+        order_type: OrderType,
+        room: RoomName,
+        resource: ResourceType,
+        price: f64,
+        amount: u32,
+    },
+    Extend {
+        order_id: String,
+        amount: u32,
+    },
+    Cancel {
+        order_id: String,
+        reason: &'static str,
+    },
+}
+
+
+/// Rooms apart, as the market counts them for transfer costs
+pub fn distance(a: RoomName, b: RoomName) -> u32 {
+    let (dx, dy) = a - b;
+    dx.abs().max(dy.abs()) as u32
+}
+
+/// Current market price of a resource, between the best buy and sell orders
+pub fn market_price(orders: &[MarketOrder]) -> Option<f64> {
+    let prices = |order_type: OrderType| -> Vec<f64> {
+        orders.iter()
+            .filter(|o| o.order_type == order_type && o.amount > 0)
+            .map(|o| o.price)
+            .collect()
+    };
+    let buy = prices(OrderType::Buy).into_iter().fold(None, |m: Option<f64>, p| Some(m.map_or(p, |m| m.max(p))));
+    let sell = prices(OrderType::Sell).into_iter().fold(None, |m: Option<f64>, p| Some(m.map_or(p, |m| m.min(p))));
+
+    match (buy, sell) {
+        (Some(b), Some(s)) => Some((b + s) / 2.0),
+        (Some(p), None) | (None, Some(p)) => Some(p),
+        (None, None) => None,
+    }
+}
+
+/// Credits we may spend: what's left of the period's budget, without going under the reserve
+pub fn available_credits(credits: f64, reserve: f64, budget: f64, spent: f64) -> f64 {
+    (credits - reserve).min(budget - spent).max(0.0)
+}
+
+/// Best deal selling up to the requested amount, if anyone pays a fair price.
+/// The terminal's energy has to cover the transfer, and for energy the amount sent too.
+pub fn best_sale(orders: &[MarketOrder], request: &TradeRequest, fair_price: f64, energy_price: f64,
+                 policy: &Policy) -> Option<Deal> {
+    let TradeRequest { room, resource, amount, terminal_energy } = *request;
+    orders.iter()
+        .filter(|o| o.order_type == OrderType::Buy && o.resource == resource)
+        .filter(|o| o.price >= fair_price * policy.min_sell_ratio)
+        .filter_map(|o| {
+            let distance = distance(room, o.room?);
+            let mut amount = amount.min(o.amount).min(policy.max_trade);
+            if resource == ResourceType::Energy {
+                amount = amount.min(terminal::max_energy_sendable(terminal_energy, distance));
+            }
+            let energy = terminal::transfer_cost(amount, distance);
+            if amount == 0 || amount < policy.min_trade || (resource != ResourceType::Energy && energy > terminal_energy) {
+                return None;
+            }
+
+            let value = o.price * amount as f64 - energy as f64 * energy_price;
+            if value <= 0.0 {
+                return None;
+            }
+            Some(Deal { order_id: o.id.clone(), room, resource, amount, price: o.price, energy, value })
+        })
+        .max_by(|a, b| (a.value / a.amount as f64).total_cmp(&(b.value / b.amount as f64)))
+}
+
+/// Cheapest deal buying up to the requested amount, if anyone sells at a fair price the budget covers.
+/// The terminal's energy has to cover the transfer.
+pub fn best_purchase(orders: &[MarketOrder], request: &TradeRequest, fair_price: f64, energy_price: f64,
+                     budget: f64, policy: &Policy) -> Option<Deal> {
+    let TradeRequest { room, resource, amount, terminal_energy } = *request;
+    orders.iter()
+        .filter(|o| o.order_type == OrderType::Sell && o.resource == resource)
+        .filter(|o| o.price <= fair_price * policy.max_buy_ratio && o.price > 0.0)
+        .filter_map(|o| {
+            let distance = distance(room, o.room?);
+            let affordable = (budget / o.price).floor() as u32;
+            let amount = amount.min(o.amount).min(policy.max_trade).min(affordable);
+            let energy = terminal::transfer_cost(amount, distance);
+            if amount == 0 || amount < policy.min_trade || energy > terminal_energy {
+                return None;
+            }
+
+            let value = -(o.price * amount as f64 + energy as f64 * energy_price);
+            Some(Deal { order_id: o.id.clone(), room, resource, amount, price: o.price, energy, value })
+        })
+        .max_by(|a, b| (a.value / a.amount as f64).total_cmp(&(b.value / b.amount as f64)))
+}
+
+/// Works out the changes to our orders: cancelling finished, stale, mispriced and unwanted ones,
+/// topping up ones still wanted, and listing new ones at the fair price within the budget
+pub fn order_actions(mine: &[MarketOrder], wanted: &[WantedOrder], prices: &HashMap<ResourceType, f64>,
+                     time: u32, budget: f64, policy: &Policy) -> Vec<OrderAction> {
+    let mut actions = Vec::new();
+    let mut budget = budget;
+    let mut listed: Vec<&WantedOrder> = Vec::new();
+
+    for order in mine {
+        let want = wanted.iter()
+            .find(|w| w.order_type == order.order_type && w.resource == order.resource && Some(w.room) == order.room);
+        let drift = prices.get(&order.resource).map(|p| (order.price - p).abs() / p);
+
+        let reason = if !order.active || order.amount == 0 {
+            Some("finished")
+        } else if time.saturating_sub(order.created) > policy.max_order_age {
+            Some("stale")
+        } else if matches!(drift, Some(d) if d > policy.max_drift) {
+            Some("mispriced")
+        } else if want.is_none() {
+            Some("unwanted")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            actions.push(OrderAction::Cancel { order_id: order.id.clone(), reason });
+            continue;
+        }
+
+        let want = want.unwrap();
+        listed.push(want);
+        let extra = want.amount.saturating_sub(order.amount);
+        let cost = order_cost(order.order_type, order.price, extra);
+        if extra >= policy.min_trade && cost <= budget {
+            budget -= cost;
+            actions.push(OrderAction::Extend { order_id: order.id.clone(), amount: extra });
+        }
+    }
+
+    for want in wanted {
+        if listed.iter().any(|l| l.order_type == want.order_type && l.resource == want.resource && l.room == want.room) {
+            continue;
+        }
+        let price = match prices.get(&want.resource) {
+            Some(&p) if p > 0.0 => p,
+            _ => continue,
+        };
+
+        let affordable = (budget / order_cost(want.order_type, price, 1)).floor() as u32;
+        let amount = want.amount.min(policy.max_trade).min(affordable);
+        if amount < policy.min_trade {
+            continue;
+        }
+        budget -= order_cost(want.order_type, price, amount);
+        actions.push(OrderAction::Create {
+            order_type: want.order_type,
+            room: want.room,
+            resource: want.resource,
+            price,
+            amount,
+        });
+    }
+
+    actions
+}
+
+/// Credits an order costs us: the listing fee, plus the price of anything we buy
+fn order_cost(order_type: OrderType, price: f64, amount: u32) -> f64 {
+    let total = price * amount as f64;
+    match order_type {
+        OrderType::Sell => total * ORDER_FEE,
+        OrderType::Buy => total * (1.0 + ORDER_FEE),
+    }
+}
+
+
+/// Amount of a resource a room has to sell, beyond its targets and what its terminal can send
+fn for_sale(room: &Room, resource: ResourceType) -> u32 {
+    let keep = match ResourceClass::of(resource) {
+        ResourceClass::Energy => config::u32_or("market.sell_energy_above", 300_000),
+        ResourceClass::Mineral | ResourceClass::Compound => stock::room_target(resource),
+        ResourceClass::Other => return 0,
+    };
+    let in_terminal = terminal::terminal(room).map(|t| t.store_of(resource)).unwrap_or(0);
+    stock::stored(room, resource).saturating_sub(keep).min(in_terminal)
+}
+
+/// Lab inputs a room is short of that none of our other rooms can send it
+fn to_buy(room: &Room, rooms: &[Room]) -> Vec<(ResourceType, u32)> {
+    terminal::needs(room).into_iter()
+        .filter(|n| n.priority == Priority::LabInput)
+        .filter(|n| !rooms.iter()
+            .filter(|r| r.name() != room.name())
+            .any(|r| stock::stored(r, n.resource) > terminal::keep(r, n.resource)))
+        .map(|n| (n.resource, n.amount))
+        .collect()
+}
+
+/// Credits left to spend this budget period, starting a new period when it's due
+fn budget() -> f64 {
+    let time = screeps::game::time();
+    let mem = memory::root();
+    let start = mem.path_i32("market.budget_start").ok().flatten().unwrap_or(0) as u32;
+    if time.saturating_sub(start) >= config::u32_or("market.budget_ticks", 10_000) {
+        mem.path_set("market.budget_start", time);
+        mem.path_set("market.spent", 0.0);
+    }
+
+    available_credits(
+        market::credits(),
+        config::u32_or("market.credit_reserve", 10_000) as f64,
+        config::u32_or("market.budget", 5_000) as f64,
+        mem.path_f64("market.spent").ok().flatten().unwrap_or(0.0),
+    )
+}
+
+/// Counts credits against this period's budget
+fn spend(credits: f64) {
+    let spent = memory::root().path_f64("market.spent").ok().flatten().unwrap_or(0.0);
+    memory::root().path_set("market.spent", spent + credits);
+}
+
+/// Writes last tick's trades, ours and others' on our orders, to metrics
+fn record_trades() {
+    let tick = screeps::game::time().saturating_sub(1);
+    for trade in util::market_trades(tick).into_iter().filter(|t| t.time == tick) {
+        let credits = (trade.price * trade.amount as f64).round() as u32;
+        info!("market: {} {} {} at {:.3}", if trade.sold { "sold" } else { "bought" },
+            trade.amount, util::resource_name(trade.resource), trade.price);
+        metrics::inc_market_trade(&util::resource_name(trade.resource), trade.sold, trade.amount, credits);
+    }
+}
+
+/// Trades on the market every few ticks, using the terminals that haven't sent anything this tick
+pub fn run(busy: &[RoomName]) {
+    record_trades();
+
+    let time = screeps::game::time();
+    if !time.is_multiple_of(config::u32_or("market.ticks", 100).max(1)) {
+        return;
+    }
+
+    let rooms: Vec<Room> = util::owned_rooms().into_iter()
+        .filter(|r| terminal::terminal(r).is_some())
+        .collect();
+    if rooms.is_empty() {
+        return;
+    }
+
+    // rooms still list orders while their terminals are busy, they just can't deal
+    let mut dealt: Vec<RoomName> = rooms.iter()
+        .filter(|r| busy.contains(&r.name()) || terminal::terminal(r).map(|t| t.cooldown() > 0).unwrap_or(true))
+        .map(|r| r.name())
+        .collect();
+
+    let mut sales = Vec::new();
+    let mut purchases = Vec::new();
+    for room in rooms.iter() {
+        let terminal_energy = terminal::terminal(room).map(|t| t.store_of(ResourceType::Energy)).unwrap_or(0);
+        for level in stock::levels(room) {
+            let amount = for_sale(room, level.resource);
+            if amount > 0 {
+                sales.push(TradeRequest { room: room.name(), resource: level.resource, amount, terminal_energy });
+            }
+        }
+        for (resource, amount) in to_buy(room, &rooms) {
+            purchases.push(TradeRequest { room: room.name(), resource, amount, terminal_energy });
+        }
+    }
+
+    // sample the prices of everything we trade, and of energy to value transfers
+    let mut resources = vec![ResourceType::Energy];
+    for request in sales.iter().chain(purchases.iter()) {
+        if !resources.contains(&request.resource) {
+            resources.push(request.resource);
+        }
+    }
+    let history_size = config::u32_or("market.history_size", 24) as usize;
+    let min_samples = config::u32_or("market.min_samples", 5) as usize;
+    let mut books = HashMap::new();
+    let mut prices = HashMap::new();
+    for resource in resources {
+        let orders = util::market_orders(resource);
+        let mut history = PriceHistory::load(resource);
+        if let Some(price) = market_price(&orders) {
+            history.record(price, history_size);
+            history.save();
+        }
+        if let Some(price) = history.fair_price(min_samples) {
+            prices.insert(resource, price);
+        }
+        books.insert(resource, orders);
+    }
+    let energy_price = match prices.get(&ResourceType::Energy) {
+        Some(&p) => p,
+        None => return,
+    };
+
+    let policy = Policy::from_config();
+    let mut budget = budget();
+    let mut wanted = Vec::new();
+
+    // each terminal makes one deal a tick, anything left over is listed as an order
+    for request in sales {
+        let (fair, orders) = match (prices.get(&request.resource), books.get(&request.resource)) {
+            (Some(&f), Some(o)) => (f, o),
+            _ => continue,
+        };
+        let deal = if dealt.contains(&request.room) {
+            None
+        } else {
+            best_sale(orders, &request, fair, energy_price, &policy)
+        };
+        match deal {
+            Some(deal) => {
+                if make_deal(&deal) {
+                    dealt.push(request.room);
+                }
+            },
+            None => wanted.push(WantedOrder {
+                order_type: OrderType::Sell,
+                room: request.room,
+                resource: request.resource,
+                amount: request.amount,
+            }),
+        }
+    }
+
+    for request in purchases {
+        let (fair, orders) = match (prices.get(&request.resource), books.get(&request.resource)) {
+            (Some(&f), Some(o)) => (f, o),
+            _ => continue,
+        };
+        let deal = if dealt.contains(&request.room) {
+            None
+        } else {
+            best_purchase(orders, &request, fair, energy_price, budget, &policy)
+        };
+        match deal {
+            Some(deal) => {
+                if make_deal(&deal) {
+                    dealt.push(request.room);
+                    budget -= deal.price * deal.amount as f64;
+                    spend(deal.price * deal.amount as f64);
+                }
+            },
+            None => wanted.push(WantedOrder {
+                order_type: OrderType::Buy,
+                room: request.room,
+                resource: request.resource,
+                amount: request.amount,
+            }),
+        }
+    }
+
+    let mine = util::my_market_orders();
+    for action in order_actions(&mine, &wanted, &prices, time, budget, &policy) {
+        apply(&action, &mine);
+    }
+}
+
+/// Makes a deal, returning whether it went through
+fn make_deal(deal: &Deal) -> bool {
+    let room = match screeps::game::rooms::get(deal.room) {
+        Some(r) => r,
+        None => return false,
+    };
+    let r = market::deal(&deal.order_id, deal.amount, &room);
+    if r == ReturnCode::Ok {
+        info!("market: {} dealing {} {} at {:.3} ({} energy, worth {:.0})",
+            deal.room, deal.amount, util::resource_name(deal.resource), deal.price, deal.energy, deal.value);
+        true
+    } else {
+        warn!("market: {} couldn't deal on order {}: {:?}", deal.room, deal.order_id, r);
+        false
+    }
+}
+
+/// Carries out a change to our orders
+fn apply(action: &OrderAction, mine: &[MarketOrder]) {
+    let r = match action {
+        OrderAction::Create { order_type, room, resource, price, amount } => {
+            let room = match screeps::game::rooms::get(*room) {
+                Some(r) => r,
+                None => return,
+            };
+            let r = market::create_order(*order_type, *resource, *price, *amount, &room);
+            if r == ReturnCode::Ok {
+                spend(order_cost(*order_type, *price, *amount));
+                info!("market: {} listed {:?} order for {} {} at {:.3}",
+                    room.name(), order_type, amount, util::resource_name(*resource), price);
+            }
+            r
+        },
+        OrderAction::Extend { order_id, amount } => {
+            let r = market::extend_order(order_id, *amount);
+            if let (ReturnCode::Ok, Some(order)) = (r, mine.iter().find(|o| &o.id == order_id)) {
+                spend(order_cost(order.order_type, order.price, *amount));
+                info!("market: extended order {} by {}", order_id, amount);
+            }
+            r
+        },
+        OrderAction::Cancel { order_id, reason } => {
+            let r = market::cancel_order(order_id);
+            if r == ReturnCode::Ok {
+                info!("market: cancelled {} order {}", reason, order_id);
+            }
+            r
+        },
+    };
+    if r != ReturnCode::Ok {
+        warn!("market: couldn't apply {:?}: {:?}", action, r);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room(name: &str) -> RoomName {
+        RoomName::new(name).unwrap()
+    }
+
+    fn order(id: &str, order_type: OrderType, from: &str, price: f64, amount: u32) -> MarketOrder {
+        MarketOrder {
+            id: id.to_string(),
+            order_type,
+            resource: ResourceType::Hydrogen,
+            room: Some(room(from)),
+            price,
+            amount,
+            created: 0,
+            active: true,
+        }
+    }
+
+    fn policy() -> Policy {
+        Policy {
+            min_sell_ratio: 0.9,
+            max_buy_ratio: 1.1,
+            max_drift: 0.2,
+            max_order_age: 10_000,
+            min_trade: 100,
+            max_trade: 10_000,
+        }
+    }
+
+    fn request(amount: u32) -> TradeRequest {
+        TradeRequest { room: room("W1N1"), resource: ResourceType::Hydrogen, amount, terminal_energy: 100_000 }
+    }
+
+    #[test]
+    fn sells_only_at_a_fair_price() {
+        let orders = vec![
+            order("cheap", OrderType::Buy, "W2N1", 0.5, 1_000),
+            order("fair", OrderType::Buy, "W2N1", 0.95, 1_000),
+        ];
+        let deal = best_sale(&orders, &request(1_000), 1.0, 0.0, &policy()).unwrap();
+        assert_eq!(deal.order_id, "fair");
+
+        let orders = vec![order("cheap", OrderType::Buy, "W2N1", 0.5, 1_000)];
+        assert!(best_sale(&orders, &request(1_000), 1.0, 0.0, &policy()).is_none());
+    }
+
+    #[test]
+    fn buys_only_at_a_fair_price() {
+        let orders = vec![
+            order("pricey", OrderType::Sell, "W2N1", 2.0, 1_000),
+            order("fair", OrderType::Sell, "W2N1", 1.05, 1_000),
+        ];
+        let deal = best_purchase(&orders, &request(1_000), 1.0, 0.0, 10_000.0, &policy()).unwrap();
+        assert_eq!(deal.order_id, "fair");
+        assert!(deal.value < 0.0);
+    }
+
+    #[test]
+    fn transfer_energy_counts_against_a_sale() {
+        // the far order pays a little more, but sending 30 rooms costs most of the amount in energy
+        let orders = vec![
+            order("far", OrderType::Buy, "W31N1", 1.05, 1_000),
+            order("near", OrderType::Buy, "W2N1", 1.0, 1_000),
+        ];
+        let deal = best_sale(&orders, &request(1_000), 1.0, 1.0, &policy()).unwrap();
+        assert_eq!(deal.order_id, "near");
+        assert_eq!(deal.energy, terminal::transfer_cost(1_000, 1));
+
+        // with free energy the better price wins
+        let deal = best_sale(&orders, &request(1_000), 1.0, 0.0, &policy()).unwrap();
+        assert_eq!(deal.order_id, "far");
+    }
+
+    #[test]
+    fn purchases_stay_within_the_budget() {
+        let orders = vec![order("sell", OrderType::Sell, "W2N1", 1.0, 5_000)];
+        let deal = best_purchase(&orders, &request(5_000), 1.0, 0.0, 500.0, &policy()).unwrap();
+        assert_eq!(deal.amount, 500);
+
+        // what the budget covers is below the smallest trade
+        assert!(best_purchase(&orders, &request(5_000), 1.0, 0.0, 50.0, &policy()).is_none());
+    }
+
+    #[test]
+    fn small_trades_are_cut_off() {
+        let orders = vec![order("small", OrderType::Buy, "W2N1", 1.0, 50)];
+        assert!(best_sale(&orders, &request(1_000), 1.0, 0.0, &policy()).is_none());
+        assert!(best_sale(&[order("big", OrderType::Buy, "W2N1", 1.0, 1_000)], &request(50), 1.0, 0.0, &policy()).is_none());
+    }
+
+    #[test]
+    fn unaffordable_orders_are_skipped_without_a_minimum_trade() {
+        let policy = Policy { min_trade: 0, ..policy() };
+        let orders = vec![
+            order("a", OrderType::Sell, "W2N1", 1.0, 1_000),
+            order("b", OrderType::Sell, "W3N1", 1.0, 1_000),
+        ];
+        assert!(best_purchase(&orders, &request(1_000), 1.0, 0.0, 0.0, &policy).is_none());
+    }
+
+    #[test]
+    fn order_actions_cancel_top_up_and_create() {
+        let wanted = |order_type, resource, amount| WantedOrder { order_type, room: room("W1N1"), resource, amount };
+        let mine_order = |id: &str, order_type, resource, price, amount, created, active| MarketOrder {
+            id: id.to_string(),
+            order_type,
+            resource,
+            room: Some(room("W1N1")),
+            price,
+            amount,
+            created,
+            active,
+        };
+
+        let mine = vec![
+            mine_order("finished", OrderType::Sell, ResourceType::Oxygen, 1.0, 0, 9_000, true),
+            mine_order("stale", OrderType::Sell, ResourceType::Utrium, 1.0, 1_000, 0, true),
+            mine_order("mispriced", OrderType::Sell, ResourceType::Keanium, 2.0, 1_000, 9_000, true),
+            mine_order("unwanted", OrderType::Sell, ResourceType::Lemergium, 1.0, 1_000, 9_000, true),
+            mine_order("short", OrderType::Sell, ResourceType::Hydrogen, 1.0, 1_000, 9_000, true),
+        ];
+        let wants = vec![
+            wanted(OrderType::Sell, ResourceType::Oxygen, 1_000),
+            wanted(OrderType::Sell, ResourceType::Utrium, 1_000),
+            wanted(OrderType::Sell, ResourceType::Keanium, 1_000),
+            wanted(OrderType::Sell, ResourceType::Hydrogen, 3_000),
+            wanted(OrderType::Buy, ResourceType::Catalyst, 1_000),
+        ];
+        let prices: HashMap<ResourceType, f64> = [
+            ResourceType::Oxygen, ResourceType::Utrium, ResourceType::Keanium, ResourceType::Lemergium,
+            ResourceType::Hydrogen, ResourceType::Catalyst,
+        ].iter().map(|r| (*r, 1.0)).collect();
+
+        let actions = order_actions(&mine, &wants, &prices, 15_000, 100_000.0, &policy());
+        let cancelled = |id: &str, why: &str| actions.iter()
+            .any(|a| matches!(a, OrderAction::Cancel { order_id, reason } if order_id == id && *reason == why));
+        assert!(cancelled("finished", "finished"));
+        assert!(cancelled("stale", "stale"));
+        assert!(cancelled("mispriced", "mispriced"));
+        assert!(cancelled("unwanted", "unwanted"));
+        assert!(actions.iter().any(|a| matches!(a, OrderAction::Extend { order_id, amount } if order_id == "short" && *amount == 2_000)));
+
+        // cancelled orders that are still wanted get listed again, alongside the new one
+        let created: Vec<ResourceType> = actions.iter()
+            .filter_map(|a| match a {
+                OrderAction::Create { resource, .. } => Some(*resource),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(created, vec![ResourceType::Oxygen, ResourceType::Utrium, ResourceType::Keanium, ResourceType::Catalyst]);
+    }
+
+    #[test]
+    fn order_actions_stay_within_the_budget() {
+        let wants = vec![WantedOrder { order_type: OrderType::Buy, room: room("W1N1"), resource: ResourceType::Catalyst, amount: 5_000 }];
+        let prices: HashMap<ResourceType, f64> = [(ResourceType::Catalyst, 1.0)].iter().cloned().collect();
+
+        // a buy order costs its price plus the fee up front
+        let actions = order_actions(&[], &wants, &prices, 0, 1_050.0, &policy());
+        assert!(matches!(actions.as_slice(), [OrderAction::Create { amount: 1_000, .. }]));
+
+        assert!(order_actions(&[], &wants, &prices, 0, 50.0, &policy()).is_empty());
+    }
+
+    #[test]
+    fn price_history_keeps_recent_samples() {
+        let mut history = PriceHistory { resource: ResourceType::Hydrogen, samples: Vec::new() };
+        for price in &[1.0, 2.0, 3.0, 4.0] {
+            history.record(*price, 3);
+        }
+        assert_eq!(history.samples, vec![2.0, 3.0, 4.0]);
+        assert_eq!(history.fair_price(3), Some(3.0));
+        assert_eq!(history.fair_price(4), None);
+    }
+}
@@ -3,3 +3,9 @@
 //! for the rooms within.
 //!
 
+pub mod deposit;
+pub mod expansion;
+pub mod intel;
+pub mod market;
+pub mod power;
+pub mod terminal;
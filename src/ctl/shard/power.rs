@@ -0,0 +1,374 @@
+//!
+//! Power bank operations
+//!
+//! Power banks turn up in the intel gathered by observers & scouts. A bank with enough power
+//! and time left gets an operation from the closest room able to field it: attacker & healer
+//! duos sized so the healer keeps up with the damage the bank reflects, enough of them to break
+//! the bank before it decays, and haulers timed to arrive as it breaks to bring the power home.
+//!
+//! Operations are kept in `Memory.power.<room>`, and called off as soon as the bank can't be
+//! broken in time.
+//!
+
+use std::str::FromStr;
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{find};
+use screeps::{Attackable, Position, ResourceType, Room, RoomName, Structure};
+use screeps::constants::*;
+use screeps::memory;
+
+use crate::ctl::creep::types::{CreepInfo, PowerAttacker, PowerHauler, PowerHealer};
+use crate::util::{self, config};
+
+use super::intel;
+
+
+/// Share of the damage dealt to a power bank that it hits back with, in percent
+const REFLECT_PERCENT: u32 = 50;
+
+
+/// Where a power operation is at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerStage {
+    /// duos are breaking the bank
+    Attacking,
+    /// the bank is broken, haulers are picking up the power
+    Hauling,
+}
+
+impl PowerStage {
+    fn as_str(self) -> &'static str {
+        match self {
+            PowerStage::Attacking => "attacking",
+            PowerStage::Hauling => "hauling",
+        }
+    }
+
+    fn from_str(s: &str) -> PowerStage {
+        match s {
+            "hauling" => PowerStage::Hauling,
+            _ => PowerStage::Attacking,
+        }
+    }
+}
+
+/// An operation to break a power bank & bring its power home
+#[derive(Debug, Clone)]
+pub struct PowerOp {
+    /// room the bank is in
+    pub room: RoomName,
+    pub pos: Position,
+    /// owned room sending creeps to the bank
+    pub home: RoomName,
+    pub power: u32,
+    pub hits: u32,
+    /// tick the bank disappears at
+    pub decays_at: u32,
+    pub stage: PowerStage,
+    /// attack parts on each attacker, matched with move parts
+    pub attack_parts: u32,
+    /// heal parts on each healer, matched with move parts
+    pub heal_parts: u32,
+    /// attacker & healer pairs working the bank
+    pub duos: u32,
+    pub haulers: u32,
+    /// carry parts on each hauler, matched with move parts
+    pub carry_parts: u32,
+}
+
+/// A creep a home room should spawn for a power operation
+#[derive(Debug, Clone)]
+pub enum PowerSpawn {
+    Attacker(PowerOp),
+    Healer(PowerOp),
+    Hauler(PowerOp),
+}
+
+
+impl PowerOp {
+    /// Loads the operation on a room's power bank, if there is one
+    pub fn load(room: RoomName) -> Option<PowerOp> {
+        let mem = memory::root().path_dict(&format!("power.{}", room)).ok()??;
+        let get = |key: &str| mem.i32(key).ok().flatten().map(|v| v as u32);
+        Some(PowerOp {
+            room,
+            pos: Position::from_packed(mem.i32("pos").ok()??),
+            home: RoomName::from_str(&mem.string("home").ok()??).ok()?,
+            power: get("power")?,
+            hits: get("hits")?,
+            decays_at: get("decays")?,
+            stage: PowerStage::from_str(&mem.string("stage").ok()?.unwrap_or_default()),
+            attack_parts: get("attack_parts")?,
+            heal_parts: get("heal_parts")?,
+            duos: get("duos")?,
+            haulers: get("haulers")?,
+            carry_parts: get("carry_parts")?,
+        })
+    }
+
+    fn save(&self) {
+        let path = format!("power.{}", self.room);
+        let mem = memory::root();
+        mem.path_set(&format!("{}.pos", path), self.pos.packed_repr());
+        mem.path_set(&format!("{}.home", path), self.home.to_string());
+        mem.path_set(&format!("{}.power", path), self.power);
+        mem.path_set(&format!("{}.hits", path), self.hits);
+        mem.path_set(&format!("{}.decays", path), self.decays_at);
+        mem.path_set(&format!("{}.stage", path), self.stage.as_str());
+        mem.path_set(&format!("{}.attack_parts", path), self.attack_parts);
+        mem.path_set(&format!("{}.heal_parts", path), self.heal_parts);
+        mem.path_set(&format!("{}.duos", path), self.duos);
+        mem.path_set(&format!("{}.haulers", path), self.haulers);
+        mem.path_set(&format!("{}.carry_parts", path), self.carry_parts);
+    }
+
+    fn set_stage(&mut self, stage: PowerStage) {
+        if self.stage != stage {
+            info!("power bank in {} (home {}): {:?} -> {:?}", self.room, self.home, self.stage, stage);
+        }
+        self.stage = stage;
+        self.save();
+    }
+
+    /// Ticks the creeps spend getting from the home room to the bank
+    fn travel_ticks(&self) -> u32 {
+        util::travel_ticks(self.home, self.room)
+    }
+
+    /// Ticks left until the bank breaks, with the given number of duos at it
+    pub fn ticks_to_break(&self, duos: u32) -> u32 {
+        ticks_to_break(self.hits, self.attack_parts, duos)
+    }
+}
+
+
+/// Sizes an attacker & healer pair for an amount of energy, as (attack parts, heal parts).
+/// The healer gets the most heal parts affordable, and the attacker only as many attack
+/// parts as the healer can keep healed through the damage the bank reflects.
+pub fn duo_size(energy: u32) -> Option<(u32, u32)> {
+    let heal = (energy / (Part::Heal.cost() + Part::Move.cost())).min(MAX_CREEP_SIZE / 2);
+    let sustainable = heal * HEAL_POWER * 100 / (ATTACK_POWER * REFLECT_PERCENT);
+    let attack = sustainable
+        .min(energy / (Part::Attack.cost() + Part::Move.cost()))
+        .min(MAX_CREEP_SIZE / 2);
+    if attack == 0 {
+        return None;
+    }
+    Some((attack, heal))
+}
+
+/// Ticks for duos with the given attack parts each to break a bank with the given hits
+pub fn ticks_to_break(hits: u32, attack_parts: u32, duos: u32) -> u32 {
+    let damage = ATTACK_POWER * attack_parts * duos;
+    if damage == 0 {
+        return u32::MAX;
+    }
+    hits.div_ceil(damage)
+}
+
+/// Fewest duos that break a bank in time, counting the time to spawn them one after another
+/// and walk to the bank, or `None` if even the most it has room for can't
+pub fn duos_needed(hits: u32, decays_in: u32, travel: u32, (attack, heal): (u32, u32), max_duos: u32) -> Option<u32> {
+    (1..=max_duos).find(|&duos| {
+        let arrival = duos * (attack + heal) * 2 * CREEP_SPAWN_TIME + travel;
+        let working = decays_in.saturating_sub(arrival).min(CREEP_LIFE_TIME.saturating_sub(travel));
+        ticks_to_break(hits, attack, duos) <= working
+    })
+}
+
+/// Haulers with the given carry parts each needed to bring the power home
+pub fn haulers_needed(power: u32, carry_parts: u32) -> u32 {
+    let capacity = carry_parts * CARRY_CAPACITY;
+    if capacity == 0 {
+        return 0;
+    }
+    power.div_ceil(capacity)
+}
+
+/// Gets all running operations
+pub fn all_ops() -> Vec<PowerOp> {
+    match memory::root().dict("power") {
+        Ok(Some(mem)) => mem.keys().iter()
+            .filter_map(|name| RoomName::from_str(name).ok())
+            .filter_map(PowerOp::load)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Calls off an operation; its creeps head home when they notice it's gone
+pub fn cancel(room: RoomName, reason: &str) {
+    warn!("calling off power bank in {}: {}", room, reason);
+    memory::root().path_del(&format!("power.{}", room));
+}
+
+/// Power left lying around a broken bank, dropped or in its ruin
+pub fn power_left(room: &Room) -> u32 {
+    let dropped: u32 = room.find(find::DROPPED_RESOURCES).iter()
+        .filter(|r| r.resource_type() == ResourceType::Power)
+        .map(|r| r.amount())
+        .sum();
+    let ruins: u32 = room.find(find::RUINS).iter()
+        .map(|r| r.store_of(ResourceType::Power))
+        .sum();
+    dropped + ruins
+}
+
+
+/// Looks for a power bank worth going after, when there's room for another operation
+pub fn plan(username: &str) {
+    let ops = all_ops();
+    if !config::bool_or("power.enabled", true) || ops.len() as u32 >= config::u32_or("power.max_ops", 1) {
+        return;
+    }
+
+    let min_rcl = config::u32_or("power.min_rcl", 7);
+    let homes: Vec<Room> = util::owned_rooms().into_iter()
+        .filter(|r| r.controller().map(|c| c.level()).unwrap_or(0) >= min_rcl)
+        .filter(|r| !ops.iter().any(|o| o.home == r.name()))
+        .collect();
+    if homes.is_empty() {
+        return;
+    }
+
+    let time = screeps::game::time();
+    let min_power = config::u32_or("power.min_power", 1_000);
+    let max_distance = config::u32_or("power.max_distance", 5);
+    let max_duos = config::u32_or("power.max_duos", 3);
+
+    let best = intel::all_intel().into_iter()
+        .filter(|i| !ops.iter().any(|o| o.room == i.name))
+        .filter(|i| !intel::should_avoid(i.name, username))
+        .flat_map(|i| i.power_banks.into_iter())
+        .filter(|b| b.power >= min_power && b.decays_at > time)
+        .filter_map(|bank| {
+            let room = bank.pos.room_name();
+            let (home, distance) = homes.iter()
+                .map(|h| (h, screeps::game::map::get_room_linear_distance(h.name(), room, false)))
+                .min_by_key(|(_, d)| *d)?;
+            if distance > max_distance {
+                return None;
+            }
+
+            let energy = home.energy_capacity_available();
+            let size = duo_size(energy)?;
+            let max_duos = max_duos.min(util::open_tiles(bank.pos));
+            let duos = duos_needed(bank.hits, bank.decays_at - time, util::travel_ticks(home.name(), room), size, max_duos)?;
+            let carry_parts = (energy / (Part::Carry.cost() + Part::Move.cost())).min(MAX_CREEP_SIZE / 2);
+
+            Some(PowerOp {
+                room,
+                pos: bank.pos,
+                home: home.name(),
+                power: bank.power,
+                hits: bank.hits,
+                decays_at: bank.decays_at,
+                stage: PowerStage::Attacking,
+                attack_parts: size.0,
+                heal_parts: size.1,
+                duos,
+                haulers: haulers_needed(bank.power, carry_parts),
+                carry_parts,
+            })
+        })
+        .max_by_key(|op| op.power);
+
+    if let Some(op) = best {
+        info!("going after {} power in {} from {}: {} duos of {} attack / {} heal, {} haulers",
+            op.power, op.room, op.home, op.duos, op.attack_parts, op.heal_parts, op.haulers);
+        op.save();
+    }
+}
+
+/// Keeps the operations up to date with what the creeps see, moves them on once the bank
+/// breaks, and calls them off when the bank can't be broken in time
+pub fn update() {
+    let time = screeps::game::time();
+    for mut op in all_ops() {
+        if let Some(room) = screeps::game::rooms::get(op.room) {
+            let bank = op.pos.look_for(screeps::look::STRUCTURES).into_iter()
+                .find_map(|s| match s {
+                    Structure::PowerBank(b) => Some(b),
+                    _ => None,
+                });
+            match (bank, op.stage) {
+                (Some(bank), _) => {
+                    op.hits = bank.hits();
+                    op.power = bank.power();
+                    op.decays_at = time + bank.ticks_to_decay();
+                    op.save();
+                },
+                (None, PowerStage::Attacking) => {
+                    if power_left(&room) > 0 {
+                        op.set_stage(PowerStage::Hauling);
+                    } else {
+                        cancel(op.room, "the bank is gone");
+                    }
+                    continue;
+                },
+                (None, PowerStage::Hauling) => {
+                    if power_left(&room) == 0 {
+                        info!("power bank in {} is cleared out", op.room);
+                        memory::root().path_del(&format!("power.{}", op.room));
+                    }
+                    continue;
+                },
+            }
+        } else if op.stage == PowerStage::Hauling && time > op.decays_at + CREEP_LIFE_TIME {
+            cancel(op.room, "the power was never picked up");
+            continue;
+        }
+
+        if op.stage == PowerStage::Attacking {
+            // duos not out yet still have to be spawned & walk over
+            let working = util::count_creeps(PowerAttacker::role(), "op", &op.room.to_string());
+            let remaining = if working == 0 {
+                op.duos * (op.attack_parts + op.heal_parts) * 2 * CREEP_SPAWN_TIME + op.travel_ticks()
+                    + op.ticks_to_break(op.duos)
+            } else {
+                op.ticks_to_break(working)
+            };
+            if time + remaining > op.decays_at {
+                cancel(op.room, "it can't be broken before it decays");
+            }
+        }
+    }
+}
+
+/// Picks the next creep a home room should spawn for its operation, if any.
+/// Haulers are held back until they'd arrive around when the bank breaks.
+pub fn next_spawn(home: &Room) -> Option<PowerSpawn> {
+    let op = all_ops().into_iter().find(|o| o.home == home.name())?;
+    let key = op.room.to_string();
+    let haulers = util::count_creeps(PowerHauler::role(), "op", &key);
+
+    match op.stage {
+        PowerStage::Attacking => {
+            let attackers = util::count_creeps(PowerAttacker::role(), "op", &key);
+            let healers = util::count_creeps(PowerHealer::role(), "op", &key);
+            // each attacker's healer comes before the next attacker, so duos leave together
+            if healers < attackers {
+                return Some(PowerSpawn::Healer(op));
+            }
+            if attackers < op.duos {
+                return Some(PowerSpawn::Attacker(op));
+            }
+
+            let hauler_arrival = op.haulers * op.carry_parts * 2 * CREEP_SPAWN_TIME + op.travel_ticks();
+            if haulers < op.haulers && op.ticks_to_break(attackers) <= hauler_arrival {
+                return Some(PowerSpawn::Hauler(op));
+            }
+            None
+        },
+        PowerStage::Hauling => {
+            if haulers < op.haulers {
+                Some(PowerSpawn::Hauler(op))
+            } else {
+                None
+            }
+        },
+    }
+}
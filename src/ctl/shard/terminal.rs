@@ -0,0 +1,190 @@
+//!
+//! Balancing resources between our rooms' terminals
+//!
+//! Rooms short of a resource are sent it by rooms holding more than their target,
+//! picking the supplier that's cheapest to send from. Shortfalls are handled in priority
+//! order: rooms running out of energy first, then lab inputs, then everything else.
+//!
+
+use log::*;
+
+use screeps::prelude::*;
+use screeps::{HasStore, ResourceType, ReturnCode, Room, RoomName, StructureTerminal};
+
+use crate::labs;
+use crate::stock::{self, ResourceClass};
+use crate::util::{self, config};
+
+
+/// How urgently a room needs a resource, most urgent first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// the room is about to run out of energy
+    Emergency,
+    /// minerals the room's reaction chains are missing
+    LabInput,
+    /// the room is under its stock target
+    Balance,
+}
+
+/// A resource a room is short of
+#[derive(Debug, Clone, Copy)]
+pub struct Need {
+    pub priority: Priority,
+    pub room: RoomName,
+    pub resource: ResourceType,
+    pub amount: u32,
+}
+
+/// A planned send from one terminal to another
+#[derive(Debug, Clone, Copy)]
+pub struct Transfer {
+    pub resource: ResourceType,
+    pub from: RoomName,
+    pub to: RoomName,
+    pub amount: u32,
+    /// energy the send costs the sending terminal
+    pub cost: u32,
+    pub priority: Priority,
+}
+
+
+/// Energy a terminal pays to send an amount of resources a number of rooms away
+pub fn transfer_cost(amount: u32, distance: u32) -> u32 {
+    (amount as f64 * (1.0 - (-(distance as f64) / 30.0).exp())).ceil() as u32
+}
+
+/// Largest amount a terminal can send when it pays the cost from the same energy it sends
+pub fn max_energy_sendable(energy: u32, distance: u32) -> u32 {
+    let rate = 1.0 - (-(distance as f64) / 30.0).exp();
+    (energy as f64 / (1.0 + rate)).floor() as u32
+}
+
+/// Gets the resources an owned room with a terminal is short of
+pub fn needs(room: &Room) -> Vec<Need> {
+    let emergency = config::u32_or("terminal.emergency_energy", 10_000);
+
+    let mut needs: Vec<Need> = stock::levels(room).into_iter()
+        .filter_map(|level| {
+            let priority = match ResourceClass::of(level.resource) {
+                ResourceClass::Energy if level.amount < emergency => Priority::Emergency,
+                ResourceClass::Energy | ResourceClass::Compound => Priority::Balance,
+                ResourceClass::Mineral | ResourceClass::Other => return None,
+            };
+            if level.shortfall() == 0 {
+                return None;
+            }
+            Some(Need { priority, room: room.name(), resource: level.resource, amount: level.shortfall() })
+        })
+        .collect();
+
+    for (resource, amount) in labs::missing(room) {
+        needs.push(Need { priority: Priority::LabInput, room: room.name(), resource, amount });
+    }
+
+    needs
+}
+
+/// Amount of a resource a room keeps for itself, rather than sending away
+pub fn keep(room: &Room, resource: ResourceType) -> u32 {
+    match ResourceClass::of(resource) {
+        ResourceClass::Mineral if labs::setup(room).is_some() => stock::terminal_target(resource),
+        ResourceClass::Mineral | ResourceClass::Other => 0,
+        ResourceClass::Energy | ResourceClass::Compound => stock::room_target(resource),
+    }
+}
+
+/// Plans sends to cover the needs, each terminal sending at most once
+pub fn plan(rooms: &[Room]) -> Vec<Transfer> {
+    let min_send = config::u32_or("terminal.min_send", 1_000);
+    let max_send = config::u32_or("terminal.max_send", 20_000);
+
+    let mut needs: Vec<Need> = rooms.iter().flat_map(needs).collect();
+    needs.sort_by_key(|n| (n.priority, std::cmp::Reverse(n.amount)));
+
+    let mut busy: Vec<RoomName> = rooms.iter()
+        .filter(|r| terminal(r).map(|t| t.cooldown() > 0).unwrap_or(true))
+        .map(|r| r.name())
+        .collect();
+
+    let mut transfers = Vec::new();
+    for need in needs {
+        let best = rooms.iter()
+            .filter(|r| r.name() != need.room && !busy.contains(&r.name()))
+            .filter_map(|r| {
+                let terminal = terminal(r)?;
+                let distance = screeps::game::map::get_room_linear_distance(r.name(), need.room, true);
+                let surplus = stock::stored(r, need.resource).saturating_sub(keep(r, need.resource));
+                let energy = terminal.store_of(ResourceType::Energy);
+
+                let mut amount = need.amount.min(surplus).min(max_send).min(terminal.store_of(need.resource));
+                if need.resource == ResourceType::Energy {
+                    amount = amount.min(max_energy_sendable(energy, distance));
+                } else if transfer_cost(amount, distance) > energy {
+                    return None;
+                }
+                if amount < min_send {
+                    return None;
+                }
+
+                Some(Transfer {
+                    resource: need.resource,
+                    from: r.name(),
+                    to: need.room,
+                    amount,
+                    cost: transfer_cost(amount, distance),
+                    priority: need.priority,
+                })
+            })
+            .min_by_key(|t| t.cost * 1_000 / t.amount.max(1));
+
+        if let Some(transfer) = best {
+            busy.push(transfer.from);
+            transfers.push(transfer);
+        }
+    }
+
+    transfers
+}
+
+/// Balances resources between our terminals, every few ticks.
+/// Returns the rooms whose terminals sent something, and are done for the tick.
+pub fn run() -> Vec<RoomName> {
+    let mut sent = Vec::new();
+    if !screeps::game::time().is_multiple_of(config::u32_or("terminal.balance_ticks", 20).max(1)) {
+        return sent;
+    }
+
+    let rooms: Vec<Room> = util::owned_rooms().into_iter()
+        .filter(|r| terminal(r).is_some())
+        .collect();
+    if rooms.len() < 2 {
+        return sent;
+    }
+
+    for transfer in plan(&rooms) {
+        let from = rooms.iter().find(|r| r.name() == transfer.from).and_then(terminal);
+        let from = match from {
+            Some(t) => t,
+            None => continue,
+        };
+
+        let r = from.send(transfer.resource, transfer.amount, &transfer.to.to_string(), Some("balance"));
+        if r == ReturnCode::Ok {
+            sent.push(transfer.from);
+            info!("sent {} {} from {} to {} ({:?}, cost {})",
+                transfer.amount, util::resource_name(transfer.resource), transfer.from, transfer.to,
+                transfer.priority, transfer.cost);
+        } else {
+            warn!("couldn't send {} from {} to {}: {:?}",
+                util::resource_name(transfer.resource), transfer.from, transfer.to, r);
+        }
+    }
+
+    sent
+}
+
+/// Gets the room's terminal, if it's ours and usable
+pub fn terminal(room: &Room) -> Option<StructureTerminal> {
+    room.terminal().filter(|t| t.my() && t.is_active())
+}
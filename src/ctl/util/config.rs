@@ -0,0 +1,33 @@
+//!
+//! Runtime configuration
+//!
+//! Values are read from `Memory.config`, so they can be changed from the console
+//! without redeploying, e.g. `Memory.config.safe_mode.dry_run = true`
+//!
+
+use screeps::memory;
+
+
+/// Gets a boolean config value, or the default if it isn't set
+pub fn bool_or(path: &str, default: bool) -> bool {
+    match memory::root().get_path::<bool>(&format!("config.{}", path)) {
+        Ok(Some(v)) => v,
+        _ => default,
+    }
+}
+
+/// Gets an unsigned integer config value, or the default if it isn't set
+pub fn u32_or(path: &str, default: u32) -> u32 {
+    match memory::root().path_i32(&format!("config.{}", path)) {
+        Ok(Some(v)) if v >= 0 => v as u32,
+        _ => default,
+    }
+}
+
+/// Gets a string config value, or the default if it isn't set
+pub fn string_or(path: &str, default: &str) -> String {
+    match memory::root().path_string(&format!("config.{}", path)) {
+        Ok(Some(v)) => v,
+        _ => default.to_string(),
+    }
+}
@@ -2,11 +2,346 @@
 //! Utility functions
 //!
 
+use std::str::FromStr;
+
 use stdweb::js;
 use stdweb::unstable::TryInto;
 
+use screeps::{Mineral, Position, PowerType, RawObjectId, ResourceType, ReturnCode, RoomName, Structure, StructureController,
+    StructureFactory};
+use screeps::game::market::OrderType;
+
 #[allow(unused)]
 pub fn random() -> u32 {
     let rnd: f64 = js! { return Math.random() }.try_into().unwrap();
     (rnd * std::u32::MAX as f64).floor() as u32
-}
\ No newline at end of file
+}
+
+/// Gets the (username, ticks to end) of a controller's reservation.
+/// Read directly, since `StructureController::reservation` looks up the wrong field for the ticks.
+pub fn reservation(controller: &StructureController) -> Option<(String, u32)> {
+    let username: Option<String> = js! {
+        const r = @{controller.as_ref()}.reservation;
+        return r ? r.username : null;
+    }.try_into().ok()?;
+    let ticks: u32 = js! {
+        const r = @{controller.as_ref()}.reservation;
+        return r ? r.ticksToEnd : 0;
+    }.try_into().ok()?;
+
+    username.map(|u| (u, ticks))
+}
+
+/// Gets the game's name for a resource type, e.g. "H" or "energy", for storing in memory.
+/// Parse it back with `ResourceType::from_str`.
+pub fn resource_name(resource: ResourceType) -> String {
+    js! {
+        return __resource_type_num_to_str(@{resource as u16});
+    }.try_into().unwrap()
+}
+
+
+/// Gets the amount left in a mineral.
+/// Read directly, since `Mineral::mineral_amount` tries to read the amount as a density.
+pub fn mineral_amount(mineral: &Mineral) -> u32 {
+    js! {
+        return @{mineral.as_ref()}.mineralAmount;
+    }.try_into().unwrap_or(0)
+}
+
+/// Gets the ticks until a depleted mineral regenerates, or 0 if it isn't regenerating
+pub fn mineral_regeneration(mineral: &Mineral) -> u32 {
+    js! {
+        return @{mineral.as_ref()}.ticksToRegeneration || 0;
+    }.try_into().unwrap_or(0)
+}
+
+/// Looks up a structure of any type by its id
+pub fn structure_from_id(id: &str) -> Option<Structure> {
+    let id = RawObjectId::from_str(id).ok()?;
+    let obj = screeps::game::get_object_erased(id)?;
+    obj.as_ref().clone().try_into().ok()
+}
+
+
+/// An order on the market, read directly since the API's order types keep their fields private
+#[derive(Debug, Clone)]
+pub struct MarketOrder {
+    pub id: String,
+    pub order_type: OrderType,
+    pub resource: ResourceType,
+    /// room the order trades from, `None` for intershard resources
+    pub room: Option<RoomName>,
+    pub price: f64,
+    /// amount still left to trade
+    pub amount: u32,
+    pub created: u32,
+    /// whether the order is listed, only ever false for our own orders
+    pub active: bool,
+}
+
+/// A completed trade against a market order, from our side
+#[derive(Debug, Clone)]
+pub struct Trade {
+    /// whether resources left our terminal, so we sold
+    pub sold: bool,
+    pub resource: ResourceType,
+    pub amount: u32,
+    pub price: f64,
+    pub time: u32,
+}
+
+/// Converts rows of `[id, type, resource, room, price, amount, created, active]` into orders
+fn market_order_rows(rows: Vec<Vec<String>>) -> Vec<MarketOrder> {
+    rows.into_iter()
+        .filter_map(|row| {
+            Some(MarketOrder {
+                id: row.first()?.clone(),
+                order_type: OrderType::from_str(row.get(1)?).ok()?,
+                resource: ResourceType::from_str(row.get(2)?).ok()?,
+                room: RoomName::new(row.get(3)?).ok(),
+                price: row.get(4)?.parse().ok()?,
+                amount: row.get(5)?.parse().ok()?,
+                created: row.get(6)?.parse().ok()?,
+                active: row.get(7)? == "true",
+            })
+        })
+        .collect()
+}
+
+/// Gets the market's orders for a resource
+pub fn market_orders(resource: ResourceType) -> Vec<MarketOrder> {
+    let rows: Vec<Vec<String>> = js! {
+        const orders = Game.market.getAllOrders({ resourceType: __resource_type_num_to_str(@{resource as u16}) });
+        return orders.map((o) => [o.id, o.type, o.resourceType, o.roomName || "", "" + o.price,
+            "" + o.remainingAmount, "" + o.created, "true"]);
+    }.try_into().unwrap_or_default();
+    market_order_rows(rows)
+}
+
+/// Gets our own market orders, active or not
+pub fn my_market_orders() -> Vec<MarketOrder> {
+    let rows: Vec<Vec<String>> = js! {
+        return Object.values(Game.market.orders).map((o) => [o.id, o.type, o.resourceType, o.roomName || "",
+            "" + o.price, "" + o.remainingAmount, "" + o.created, "" + o.active]);
+    }.try_into().unwrap_or_default();
+    market_order_rows(rows)
+}
+
+/// Gets the trades against market orders made since a tick, ours or others' dealing on ours
+pub fn market_trades(since: u32) -> Vec<Trade> {
+    let rows: Vec<Vec<String>> = js! {
+        const since = @{since};
+        const rows = (list, sold) => list
+            .filter((t) => t.order && t.time >= since)
+            .map((t) => ["" + sold, t.resourceType, "" + t.amount, "" + t.order.price, "" + t.time]);
+        return rows(Game.market.outgoingTransactions, true).concat(rows(Game.market.incomingTransactions, false));
+    }.try_into().unwrap_or_default();
+
+    rows.into_iter()
+        .filter_map(|row| {
+            Some(Trade {
+                sold: row.first()? == "true",
+                resource: ResourceType::from_str(row.get(1)?).ok()?,
+                amount: row.get(2)?.parse().ok()?,
+                price: row.get(3)?.parse().ok()?,
+                time: row.get(4)?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+
+/// One of our power creeps, read directly since the API has no bindings for power creeps
+#[derive(Debug, Clone)]
+pub struct PowerCreepState {
+    pub name: String,
+    pub level: u32,
+    /// where the creep is, `None` while it isn't spawned
+    pub pos: Option<Position>,
+    pub ticks_to_live: u32,
+    /// ops carried
+    pub ops: u32,
+    pub free_capacity: u32,
+    /// (power, level, cooldown) of each power it has
+    pub powers: Vec<(PowerType, u32, u32)>,
+}
+
+/// Translates a `PWR_*` constant's number into its power
+fn power_type(id: u32) -> Option<PowerType> {
+    use PowerType::*;
+    [GenerateOps, OperateSpawn, OperateTower, OperateStorage, OperateLab, OperateExtension, OperateObserve,
+        OperateTerminal, DisruptSpawn, DisruptTower, Shield, RegenSource, RegenMineral, DisruptTerminal,
+        OperatePower, Fortify, OperateController, OperateFactory]
+        .iter()
+        .find(|p| **p as u32 == id)
+        .cloned()
+}
+
+/// Gets the global power level, which limits the power creeps & powers we can have
+pub fn gpl_level() -> u32 {
+    js! {
+        return Game.gpl.level;
+    }.try_into().unwrap_or(0)
+}
+
+/// Gets all our power creeps, spawned or not
+pub fn power_creeps() -> Vec<PowerCreepState> {
+    let rows: Vec<Vec<String>> = js! {
+        return Object.values(Game.powerCreeps).map((c) => [c.name, "" + c.level,
+            c.room ? c.room.name : "", c.pos ? "" + c.pos.x : "", c.pos ? "" + c.pos.y : "",
+            "" + (c.ticksToLive || 0), "" + c.store.getUsedCapacity(RESOURCE_OPS), "" + c.store.getFreeCapacity()]);
+    }.try_into().unwrap_or_default();
+
+    rows.into_iter()
+        .filter_map(|row| {
+            let name = row.first()?.clone();
+            let pos = match (RoomName::new(row.get(2)?), row.get(3)?.parse(), row.get(4)?.parse()) {
+                (Ok(room), Ok(x), Ok(y)) => Some(Position::new(x, y, room)),
+                _ => None,
+            };
+            let powers: Vec<Vec<u32>> = js! {
+                const powers = Game.powerCreeps[@{name.as_str()}].powers;
+                return Object.keys(powers).map((p) => [+p, powers[p].level, powers[p].cooldown || 0]);
+            }.try_into().unwrap_or_default();
+            Some(PowerCreepState {
+                level: row.get(1)?.parse().ok()?,
+                pos,
+                ticks_to_live: row.get(5)?.parse().ok()?,
+                ops: row.get(6)?.parse().ok()?,
+                free_capacity: row.get(7)?.parse().ok()?,
+                powers: powers.into_iter()
+                    .filter_map(|p| Some((power_type(*p.first()?)?, *p.get(1)?, *p.get(2)?)))
+                    .collect(),
+                name,
+            })
+        })
+        .collect()
+}
+
+/// Creates a new operator power creep, which then has to be spawned
+pub fn create_power_creep(name: &str) -> ReturnCode {
+    js! {
+        return PowerCreep.create(@{name}, POWER_CLASS.OPERATOR);
+    }.try_into().unwrap_or(ReturnCode::InvalidArgs)
+}
+
+/// Spawns a power creep at a power spawn
+pub fn spawn_power_creep(name: &str, power_spawn_id: &str) -> ReturnCode {
+    js! {
+        return Game.powerCreeps[@{name}].spawn(Game.getObjectById(@{power_spawn_id}));
+    }.try_into().unwrap_or(ReturnCode::InvalidArgs)
+}
+
+/// Renews a power creep at a power spawn
+pub fn renew_power_creep(name: &str, power_spawn_id: &str) -> ReturnCode {
+    js! {
+        return Game.powerCreeps[@{name}].renew(Game.getObjectById(@{power_spawn_id}));
+    }.try_into().unwrap_or(ReturnCode::InvalidArgs)
+}
+
+/// Spends a global power level on a power creep's power
+pub fn upgrade_power_creep(name: &str, power: PowerType) -> ReturnCode {
+    js! {
+        return Game.powerCreeps[@{name}].upgrade(@{power as u32});
+    }.try_into().unwrap_or(ReturnCode::InvalidArgs)
+}
+
+/// Has a power creep use a power, on the object with the given id if the power takes a target
+pub fn use_power(name: &str, power: PowerType, target_id: Option<&str>) -> ReturnCode {
+    js! {
+        const id = @{target_id};
+        return Game.powerCreeps[@{name}].usePower(@{power as u32}, id ? Game.getObjectById(id) : undefined);
+    }.try_into().unwrap_or(ReturnCode::InvalidArgs)
+}
+
+/// Has a power creep enable powers on a room's controller
+pub fn enable_room(name: &str, controller: &StructureController) -> ReturnCode {
+    js! {
+        return Game.powerCreeps[@{name}].enableRoom(@{controller.as_ref()});
+    }.try_into().unwrap_or(ReturnCode::InvalidArgs)
+}
+
+/// Moves a power creep towards a position
+pub fn move_power_creep(name: &str, pos: Position) -> ReturnCode {
+    js! {
+        return Game.powerCreeps[@{name}].moveTo(new RoomPosition(@{pos.x()}, @{pos.y()}, @{pos.room_name().to_string()}));
+    }.try_into().unwrap_or(ReturnCode::InvalidArgs)
+}
+
+/// Has a power creep take from (or, with `deliver`, put into) the object with the given id
+pub fn power_creep_transfer(name: &str, target_id: &str, resource: ResourceType, amount: u32, deliver: bool) -> ReturnCode {
+    js! {
+        const creep = Game.powerCreeps[@{name}];
+        const target = Game.getObjectById(@{target_id});
+        const resource = __resource_type_num_to_str(@{resource as u16});
+        return @{deliver} ? creep.transfer(target, resource, @{amount}) : creep.withdraw(target, resource, @{amount});
+    }.try_into().unwrap_or(ReturnCode::InvalidArgs)
+}
+
+/// Whether the object with the given id is under a power's effect
+pub fn has_effect(id: &str, power: PowerType) -> bool {
+    js! {
+        const obj = Game.getObjectById(@{id});
+        return !!(obj && obj.effects && obj.effects.some((e) => e.effect === @{power as u32}));
+    }.try_into().unwrap_or(false)
+}
+
+/// Whether powers can be used in a controller's room
+pub fn power_enabled(controller: &StructureController) -> bool {
+    js! {
+        return !!@{controller.as_ref()}.isPowerEnabled;
+    }.try_into().unwrap_or(false)
+}
+
+
+/// A factory recipe, read from the game's `COMMODITIES` since the API doesn't carry them
+#[derive(Debug, Clone)]
+pub struct Commodity {
+    pub product: ResourceType,
+    /// amount made by one run
+    pub amount: u32,
+    pub cooldown: u32,
+    /// factory level the recipe needs, `None` if any factory can make it
+    pub level: Option<u32>,
+    /// (resource, amount) used up by one run
+    pub components: Vec<(ResourceType, u32)>,
+}
+
+/// Gets everything a factory can produce
+pub fn commodity_products() -> Vec<ResourceType> {
+    let names: Vec<String> = js! {
+        return Object.keys(COMMODITIES);
+    }.try_into().unwrap_or_default();
+    names.iter().filter_map(|n| ResourceType::from_str(n).ok()).collect()
+}
+
+/// Gets the recipe for a factory product
+pub fn commodity(product: ResourceType) -> Option<Commodity> {
+    // [amount, cooldown, level, component, amount, component, amount, ...]
+    let row: Vec<String> = js! {
+        const c = COMMODITIES[__resource_type_num_to_str(@{product as u16})];
+        if (!c) {
+            return [];
+        }
+        const components = Object.keys(c.components).map((r) => [r, "" + c.components[r]]);
+        return ["" + c.amount, "" + c.cooldown, c.level === undefined ? "" : "" + c.level].concat(...components);
+    }.try_into().ok()?;
+
+    Some(Commodity {
+        product,
+        amount: row.first()?.parse().ok()?,
+        cooldown: row.get(1)?.parse().ok()?,
+        level: row.get(2)?.parse().ok(),
+        components: row.get(3..)?.chunks(2)
+            .filter_map(|pair| Some((ResourceType::from_str(pair.first()?).ok()?, pair.get(1)?.parse().ok()?)))
+            .collect(),
+    })
+}
+
+/// Gets a factory's level, 0 until a power creep has operated it
+pub fn factory_level(factory: &StructureFactory) -> u32 {
+    js! {
+        return @{factory.as_ref()}.level || 0;
+    }.try_into().unwrap_or(0)
+}
@@ -69,6 +69,109 @@ pub fn inc_builders(count: u32) {
     inc_count("builder_creeps", count);
 }
 
+/// Increment the number of defender creeps spawned this tick
+pub fn inc_defenders(count: u32) {
+    inc_count("defender_creeps", count);
+}
+
+/// Increment the number of attacker creeps spawned this tick
+pub fn inc_attackers(count: u32) {
+    inc_count("attacker_creeps", count);
+}
+
+/// Increment the number of creeps spawned for a remote room this tick
+pub fn inc_remote_creeps(room: &str, count: u32) {
+    inc_count(&format!("remote_{}_creeps", room), count);
+}
+
+/// Increment the amount of energy brought home from a remote room this tick
+pub fn inc_remote_energy(room: &str, count: u32) {
+    inc_count(&format!("remote_{}_energy", room), count);
+}
+
+/// Increment the number of creeps spawned for an expansion this tick
+pub fn inc_expansion_creeps(count: u32) {
+    inc_count("expansion_creeps", count);
+}
+
+/// Increment the number of scout creeps spawned this tick
+pub fn inc_scouts(count: u32) {
+    inc_count("scout_creeps", count);
+}
+
+/// Increment the number of mineral miner creeps spawned this tick
+pub fn inc_mineral_miners(count: u32) {
+    inc_count("mineral_miner_creeps", count);
+}
+
+/// Increment the number of carrier creeps spawned this tick
+pub fn inc_carriers(count: u32) {
+    inc_count("carrier_creeps", count);
+}
+
+/// Increment the number of upgrader creeps spawned this tick
+pub fn inc_upgraders(count: u32) {
+    inc_count("upgrader_creeps", count);
+}
+
+/// Increment the number of creeps spawned for power bank operations this tick
+pub fn inc_power_creeps(count: u32) {
+    inc_count("power_creeps", count);
+}
+
+/// Increment the amount of power brought home from power banks this tick
+pub fn inc_power_harvested(count: u32) {
+    inc_count("power_harvested", count);
+}
+
+/// Increment the number of creeps spawned for deposit operations this tick
+pub fn inc_deposit_creeps(count: u32) {
+    inc_count("deposit_creeps", count);
+}
+
+/// Increment the amount of a deposit resource brought home this tick
+pub fn inc_deposit_harvested(resource: &str, amount: u32) {
+    inc_count(&format!("deposit_{}", resource), amount);
+}
+
+/// Increment the amount of a product made by a factory this tick
+pub fn inc_factory_produced(resource: &str, amount: u32) {
+    inc_count(&format!("factory_{}", resource), amount);
+}
+
+/// Increment the amount of power processed this tick
+pub fn inc_power_processed(count: u32) {
+    inc_count("power_processed", count);
+}
+
+/// Increment the number of resources a room holds more & less of than its targets
+pub fn inc_stock_off_target(room: &str, over: u32, under: u32) {
+    inc_count(&format!("stock_{}_over", room), over);
+    inc_count(&format!("stock_{}_under", room), under);
+}
+
+/// Increment the amount of a resource sold or bought on the market this tick, and the credits it made or cost
+pub fn inc_market_trade(resource: &str, sold: bool, amount: u32, credits: u32) {
+    let side = if sold { "sold" } else { "bought" };
+    inc_count(&format!("market_{}_{}", side, resource), amount);
+    inc_count(&format!("market_{}_credits", side), credits);
+}
+
+/// Increment the number of safe mode activations this tick
+pub fn inc_safe_modes(count: u32) {
+    inc_count("safe_mode_activations", count);
+}
+
+/// Increment the number of rooms that went into recovery this tick
+pub fn inc_recoveries(count: u32) {
+    inc_count("recoveries", count);
+}
+
+/// Increment the number of rooms recovering from losing their economy this tick
+pub fn inc_rooms_recovering(count: u32) {
+    inc_count("rooms_recovering", count);
+}
+
 /// Increment the amount of energy stored this tick
 pub fn inc_energy(count: u32) {
     inc_count("energy", count);
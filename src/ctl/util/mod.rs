@@ -1,15 +1,19 @@
 use std::str::FromStr;
 
-use screeps::{HasId, ObjectId, SizedRoomObject};
+use screeps::prelude::*;
+use screeps::{ObjectId, Position, Room, RoomName, SizedRoomObject, Terrain};
 use screeps::memory::MemoryReference;
 
 
+pub mod config;
 mod js;
 pub mod metrics;
 
 pub use js::*;
 
 
+/// Ticks a room is assumed to take to cross
+const ROOM_TRAVEL_TICKS: u32 = 50;
 
 
 pub fn obj_from_mem_id<T: HasId + SizedRoomObject>(mem: MemoryReference, name: &str) -> Option<T> {
@@ -21,4 +25,80 @@ pub fn obj_from_mem_id<T: HasId + SizedRoomObject>(mem: MemoryReference, name: &
         .map(|id| id.try_resolve().ok())
         .flatten()
         .flatten()
+}
+
+/// Gets our username, taken from one of our spawns
+pub fn my_username() -> Option<String> {
+    screeps::game::spawns::values().first().and_then(|s| s.owner_name())
+}
+
+/// Gets all rooms with a controller we own
+pub fn owned_rooms() -> Vec<Room> {
+    screeps::game::rooms::values().into_iter()
+        .filter(|r| matches!(r.controller(), Some(c) if c.my()))
+        .collect()
+}
+
+/// Ticks a creep is assumed to take to walk between two rooms, from their linear distance
+pub fn travel_ticks(from: RoomName, to: RoomName) -> u32 {
+    screeps::game::map::get_room_linear_distance(from, to, false) * ROOM_TRAVEL_TICKS
+}
+
+/// Gets the center position of a room, handy as a travel target for rooms without vision
+pub fn room_center(room_name: RoomName) -> Position {
+    Position::new(25, 25, room_name)
+}
+
+/// Number of tiles around a position creeps can stand on
+pub fn open_tiles(pos: Position) -> u32 {
+    let terrain = screeps::game::map::get_room_terrain(pos.room_name());
+    let (x, y) = (pos.x() as i32, pos.y() as i32);
+    let mut open = 0;
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            let (ax, ay) = (x + dx, y + dy);
+            if (dx, dy) == (0, 0) || !(1..49).contains(&ax) || !(1..49).contains(&ay) {
+                continue;
+            }
+            if terrain.get(ax as u32, ay as u32) != Terrain::Wall {
+                open += 1;
+            }
+        }
+    }
+    open
+}
+
+/// Gets the horizontal & vertical numbers of a room name, e.g. (12, 3) for W12N3
+fn room_coords(room_name: RoomName) -> Option<(u32, u32)> {
+    let name = room_name.to_string();
+    let mut parts = name[1..].split(&['N', 'S'][..]);
+    let x = parts.next()?.parse().ok()?;
+    let y = parts.next()?.parse().ok()?;
+    Some((x, y))
+}
+
+/// Whether the room is a highway room, between sectors
+pub fn is_highway(room_name: RoomName) -> bool {
+    matches!(room_coords(room_name), Some((x, y)) if x % 10 == 0 || y % 10 == 0)
+}
+
+/// Whether the room is one of the source keeper rooms around a sector's center
+pub fn is_source_keeper(room_name: RoomName) -> bool {
+    match room_coords(room_name) {
+        Some((x, y)) => {
+            let (x, y) = (x % 10, y % 10);
+            (4..=6).contains(&x) && (4..=6).contains(&y) && !(x == 5 && y == 5)
+        },
+        None => false,
+    }
+}
+
+/// Counts our creeps with the given role, and with a memory key set to the given value.
+/// Creeps retiring from their job aren't counted, so their replacement gets spawned.
+pub fn count_creeps(role: &str, key: &str, value: &str) -> u32 {
+    screeps::game::creeps::values().iter()
+        .filter(|c| !c.memory().bool("retiring"))
+        .filter(|c| c.memory().string("role").ok().flatten().as_deref() == Some(role))
+        .filter(|c| c.memory().string(key).ok().flatten().as_deref() == Some(value))
+        .count() as u32
 }
\ No newline at end of file
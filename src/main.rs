@@ -54,38 +54,123 @@ fn game_loop() {
     // determine their roles, handle tasks
     trace!("running creeps");
 
-    let mut harvesters = 0;
-    let mut builders = 0;
     for creep in screeps::game::creeps::values() {
         if !creep.memory().bool("ignore") || creep.ticks_to_live() == 0 {
+            if ctl::lifecycle::run(&creep) {
+                continue;
+            }
+            if ctl::boosting::run(&creep) {
+                continue;
+            }
+            if ctl::renewal::run(&creep) {
+                continue;
+            }
             if let Ok(Some(role)) = creep.memory().string("role") {
                 if role == BasicHarvester::role() {
                     ctl::harvester::run_basic_harvester(creep);
-                    harvesters += 1;
                 } else if role == BasicBuilder::role() {
                     ctl::builder::run_basic_builder(creep);
-                    builders += 1;
+                } else if role == MeleeDefender::role()
+                    || role == RangedDefender::role()
+                    || role == HealerDefender::role() {
+                    ctl::defender::run_defender(creep);
+                } else if role == CoreAttacker::role() {
+                    ctl::attacker::run_core_attacker(creep);
+                } else if role == Reserver::role() {
+                    ctl::reserver::run_reserver(creep);
+                } else if role == RemoteMiner::role() {
+                    ctl::miner::run_remote_miner(creep);
+                } else if role == RemoteHauler::role() {
+                    ctl::hauler::run_remote_hauler(creep);
+                } else if role == Claimer::role() {
+                    ctl::claimer::run_claimer(creep);
+                } else if role == Pioneer::role() {
+                    ctl::pioneer::run_pioneer(creep);
+                } else if role == Carrier::role() {
+                    ctl::carrier::run_carrier(creep);
+                } else if role == Upgrader::role() {
+                    ctl::upgrader::run_upgrader(creep);
+                } else if role == MineralMiner::role() {
+                    ctl::miner::run_mineral_miner(creep);
+                } else if role == Scout::role() {
+                    ctl::scout::run_scout(creep);
+                } else if role == PowerAttacker::role() {
+                    ctl::powerbank::run_power_attacker(creep);
+                } else if role == PowerHealer::role() {
+                    ctl::powerbank::run_power_healer(creep);
+                } else if role == PowerHauler::role() {
+                    ctl::powerbank::run_power_hauler(creep);
+                } else if role == DepositHarvester::role() {
+                    ctl::miner::run_deposit_harvester(creep);
+                } else if role == DepositHauler::role() {
+                    ctl::hauler::run_deposit_hauler(creep);
                 }
             }
         }
     }
 
-    // run spawns next, using any info gathered from number of creps per role
+    // run spawns next, counting creeps per home room & role
     trace!("running spawns");
-    for room in screeps::game::rooms::values() {
-        let r = ctl::roomctl::RoomCtl::new(&room);
-
-        // these need to be ranked from highest to lowest priority
-        // TODO: manage the strategy in a more cohesive way
-        if harvesters < r.energy_spots(true) {
-            r.manage_spawns(ctl::roomctl::SpawnStrategy::CtrlrUpgrade);
-        } else if builders < (r.construction_sites()/2) {
-            r.manage_spawns(ctl::roomctl::SpawnStrategy::Builders);
+    let census = ctl::census::Census::take();
+    let safe_mode_policy = ctl::safemode::SafeModePolicy::load();
+    let username = ctl::util::my_username();
+    let time = screeps::game::time();
+    if let Some(username) = &username {
+        ctl::remote::refresh(username);
+        if time % 500 == 11 {
+            ctl::expansion::plan(username);
         }
+        if time % 100 == 31 {
+            ctl::power::plan(username);
+        }
+        if time % 100 == 61 {
+            ctl::deposit::plan(username);
+        }
+    }
+    ctl::expansion::update();
+    ctl::power::update();
+    ctl::deposit::update();
+    if time % 100 == 41 {
+        ctl::operator::manage();
     }
+    for power_creep in ctl::util::power_creeps() {
+        ctl::operator::run(&power_creep);
+    }
+    let sent = ctl::terminal::run();
+    ctl::market::run(&sent);
+    for room in screeps::game::rooms::values() {
+        let r = ctl::roomctl::RoomCtl::new(&room, &census);
 
+        ctl::intel::record(&room);
 
-    let time = screeps::game::time();
+        if let Some(username) = &username {
+            ctl::invader::scan(&room, username);
+            ctl::remote::scan(&room, username);
+            ctl::observer::run(&room, username);
+        }
+        ctl::nuke::scan(&room);
+        if r.is_owned() {
+            ctl::labs::run(&room);
+            ctl::factory::run(&room);
+            ctl::powerspawn::run(&room);
+        }
+        if time % 100 == 7 && r.is_owned() {
+            ctl::remote::discover(&room);
+            ctl::mineral::plan(&room);
+        }
+        if time % 500 == 9 && r.is_owned() {
+            ctl::stock::report(&room);
+        }
+
+        let threat = r.threat();
+        r.guard_safe_mode(&threat, &safe_mode_policy);
+
+        ctl::recovery::update(&room, &census);
+        let queue = r.spawn_queue(threat, time);
+        if !queue.is_empty() {
+            r.manage_spawns(queue);
+        }
+    }
 
     if time % 32 == 3 {
         info!("running memory cleanup");